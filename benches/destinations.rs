@@ -0,0 +1,46 @@
+//! Benchmarks for matching destinations against growing numbers of subscriptions.
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use stomp_parser::destination::{DestinationMatcher, WildcardDialect};
+
+const REGIONS: [&str; 4] = ["eu", "us", "asia", "latam"];
+
+/// One subscription per instrument, plus a wildcard subscription for every tenth one.
+fn matcher(subscriptions: usize) -> DestinationMatcher<usize> {
+    let mut matcher = DestinationMatcher::new(WildcardDialect::ACTIVEMQ);
+
+    for id in 0..subscriptions {
+        let region = REGIONS[id % REGIONS.len()];
+        matcher.insert(&format!("/topic/prices.{}.instrument-{}", region, id), id);
+        if id % 10 == 0 {
+            matcher.insert(&format!("/topic/prices.*.instrument-{}", id), id);
+            matcher.insert(&format!("/topic/prices.{}.>", region), id);
+        }
+    }
+
+    matcher
+}
+
+fn matches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("match");
+
+    for subscriptions in [100, 1_000, 10_000] {
+        let matcher = matcher(subscriptions);
+
+        group.bench_with_input(
+            BenchmarkId::from_parameter(subscriptions),
+            &matcher,
+            |b, matcher| {
+                b.iter(|| {
+                    matcher
+                        .matches(black_box("/topic/prices.us.instrument-41"))
+                        .len()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, matches);
+criterion_main!(benches);
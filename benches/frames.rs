@@ -0,0 +1,101 @@
+//! Benchmarks for parsing and building typical frames.
+use std::convert::TryFrom;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use stomp_parser::client::{ClientFrame, SendFrame};
+use stomp_parser::server::{MessageFrame, ServerFrame};
+
+const SEND: &[u8] = b"SEND\n\
+    destination:/queue/orders\n\
+    content-type:application/json\n\
+    content-length:44\n\
+    receipt:rcpt-17\n\
+    \n\
+    {\"order\":1234,\"item\":\"widget\",\"quantity\":7}\x00";
+
+const MESSAGE: &[u8] = b"MESSAGE\n\
+    message-id:ID\\cbroker-1-1234\n\
+    destination:/queue/orders\n\
+    subscription:sub-0\n\
+    content-type:application/json\n\
+    content-length:44\n\
+    \n\
+    {\"order\":1234,\"item\":\"widget\",\"quantity\":7}\x00";
+
+fn header_heavy() -> Vec<u8> {
+    let mut frame = b"MESSAGE\n\
+        message-id:ID\\cbroker-1-1234\n\
+        destination:/topic/prices.eu.equities\n\
+        subscription:sub-0\n\
+        content-type:text/plain\n"
+        .to_vec();
+
+    for i in 0..32 {
+        frame.extend_from_slice(
+            format!("x-property-{}:value\\cwith\\nescapes-{}\n", i, i).as_bytes(),
+        );
+    }
+    frame.extend_from_slice(b"\n42.17\x00");
+    frame
+}
+
+fn parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for (name, bytes) in [
+        ("send", SEND.to_vec()),
+        ("message", MESSAGE.to_vec()),
+        ("header_heavy_message", header_heavy()),
+    ] {
+        group.throughput(Throughput::Bytes(bytes.len() as u64));
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || bytes.clone(),
+                |bytes| {
+                    if name == "send" {
+                        assert!(black_box(ClientFrame::try_from(bytes)).is_ok());
+                    } else {
+                        assert!(black_box(ServerFrame::try_from(bytes)).is_ok());
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn build(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build");
+
+    group.bench_function("send", |b| {
+        b.iter(|| {
+            let frame = SendFrame::builder()
+                .destination("/queue/orders".to_owned())
+                .content_type("application/json".to_owned())
+                .receipt("rcpt-17".to_owned())
+                .body(b"{\"order\":1234,\"item\":\"widget\",\"quantity\":7}".to_vec())
+                .build();
+            black_box(Vec::<u8>::from(frame))
+        })
+    });
+
+    group.bench_function("message", |b| {
+        b.iter(|| {
+            let frame = MessageFrame::builder()
+                .message_id("ID:broker-1-1234".to_owned())
+                .destination("/queue/orders".to_owned())
+                .subscription("sub-0".to_owned())
+                .content_type("application/json".to_owned())
+                .body(b"{\"order\":1234,\"item\":\"widget\",\"quantity\":7}".to_vec())
+                .build();
+            black_box(Vec::<u8>::from(frame))
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse, build);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+#![no_main]
+//! Builds an arbitrary frame, serialises it, and checks that parsing the result yields the same
+//! kind of frame, which serialises to the same bytes.
+use std::convert::TryFrom;
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use stomp_parser::client::*;
+use stomp_parser::server::*;
+
+#[derive(Arbitrary)]
+enum Builder {
+    Abort(AbortFrameBuilder),
+    Ack(AckFrameBuilder),
+    Begin(BeginFrameBuilder),
+    Commit(CommitFrameBuilder),
+    Connect(ConnectFrameBuilder),
+    Disconnect(DisconnectFrameBuilder),
+    Nack(NackFrameBuilder),
+    Send(SendFrameBuilder),
+    Subscribe(SubscribeFrameBuilder),
+    Unsubscribe(UnsubscribeFrameBuilder),
+    Connected(ConnectedFrameBuilder),
+    Receipt(ReceiptFrameBuilder),
+    Error(ErrorFrameBuilder),
+    Message(MessageFrameBuilder),
+}
+
+/// Builders do not implement `Debug`, so failing inputs are reported by the kind of frame only
+impl std::fmt::Debug for Builder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Builder::Abort(_) => "Abort",
+            Builder::Ack(_) => "Ack",
+            Builder::Begin(_) => "Begin",
+            Builder::Commit(_) => "Commit",
+            Builder::Connect(_) => "Connect",
+            Builder::Disconnect(_) => "Disconnect",
+            Builder::Nack(_) => "Nack",
+            Builder::Send(_) => "Send",
+            Builder::Subscribe(_) => "Subscribe",
+            Builder::Unsubscribe(_) => "Unsubscribe",
+            Builder::Connected(_) => "Connected",
+            Builder::Receipt(_) => "Receipt",
+            Builder::Error(_) => "Error",
+            Builder::Message(_) => "Message",
+        })
+    }
+}
+
+macro_rules! check_roundtrip {
+    ($builder:ident, $group:ident, $variant:ident) => {{
+        let bytes: Vec<u8> = $builder.build().into();
+
+        match $group::try_from(bytes.clone()) {
+            Ok($group::$variant(frame)) => {
+                let reserialised: Vec<u8> = frame.into();
+                assert_eq!(bytes, reserialised);
+            }
+            Ok(other) => panic!("Parsed as the wrong frame: {:?}", other),
+            Err(error) => panic!(
+                "Failed to parse built frame {:?}: {}",
+                String::from_utf8_lossy(&bytes),
+                error.message()
+            ),
+        }
+    }};
+}
+
+fuzz_target!(|builder: Builder| {
+    match builder {
+        Builder::Abort(builder) => check_roundtrip!(builder, ClientFrame, Abort),
+        Builder::Ack(builder) => check_roundtrip!(builder, ClientFrame, Ack),
+        Builder::Begin(builder) => check_roundtrip!(builder, ClientFrame, Begin),
+        Builder::Commit(builder) => check_roundtrip!(builder, ClientFrame, Commit),
+        Builder::Connect(builder) => check_roundtrip!(builder, ClientFrame, Connect),
+        Builder::Disconnect(builder) => check_roundtrip!(builder, ClientFrame, Disconnect),
+        Builder::Nack(builder) => check_roundtrip!(builder, ClientFrame, Nack),
+        Builder::Send(builder) => check_roundtrip!(builder, ClientFrame, Send),
+        Builder::Subscribe(builder) => check_roundtrip!(builder, ClientFrame, Subscribe),
+        Builder::Unsubscribe(builder) => check_roundtrip!(builder, ClientFrame, Unsubscribe),
+        Builder::Connected(builder) => check_roundtrip!(builder, ServerFrame, Connected),
+        Builder::Receipt(builder) => check_roundtrip!(builder, ServerFrame, Receipt),
+        Builder::Error(builder) => check_roundtrip!(builder, ServerFrame, Error),
+        Builder::Message(builder) => check_roundtrip!(builder, ServerFrame, Message),
+    }
+});
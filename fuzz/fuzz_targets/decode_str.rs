@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use stomp_parser::fuzzing::decode_str;
+
+fuzz_target!(|data: &str| {
+    let _ = decode_str(data);
+});
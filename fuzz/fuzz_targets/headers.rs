@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use stomp_parser::fuzzing::parse_headers;
+
+fuzz_target!(|data: &[u8]| {
+    parse_headers(data);
+});
@@ -0,0 +1,21 @@
+#![no_main]
+use std::collections::HashMap;
+
+use libfuzzer_sys::fuzz_target;
+use stomp_parser::selector::{Selector, Value};
+
+fuzz_target!(|data: &str| {
+    if let Ok(selector) = Selector::parse(data) {
+        let properties: HashMap<String, Value> = [
+            ("a", Value::Integer(i64::MIN)),
+            ("b", Value::Float(f64::NAN)),
+            ("c", Value::String("%_\\".to_owned())),
+            ("d", Value::Boolean(true)),
+        ]
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.clone()))
+        .collect();
+
+        selector.matches(&properties);
+    }
+});
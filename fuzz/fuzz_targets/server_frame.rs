@@ -0,0 +1,13 @@
+#![no_main]
+use std::convert::TryFrom;
+
+use libfuzzer_sys::fuzz_target;
+use stomp_parser::server::ServerFrame;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(frame) = ServerFrame::try_from(data.to_vec()) {
+        // A parsed frame retains the bytes it was parsed from
+        let bytes: Vec<u8> = frame.into();
+        assert_eq!(data, bytes.as_slice());
+    }
+});
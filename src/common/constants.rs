@@ -3,3 +3,7 @@ pub const LINE_SEPARATOR: &[u8] = b"\n";
 pub const TERMINATOR: &[u8] = b"\x00";
 
 pub const EMPTY: [u8; 0] = [];
+
+/// The bytes reserved for each typed header line when a frame is written, as its length is only known once its value
+/// is formatted.
+pub const ESTIMATED_HEADER_LEN: usize = 32;
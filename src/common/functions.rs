@@ -1,3 +1,6 @@
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
 use either::Either;
 
 use crate::error::StompParseError;
@@ -10,45 +13,121 @@ pub fn extend_from_vec(bytes: &mut Vec<u8>, extension: &mut Vec<u8>) -> (usize,
     (begin, bytes.len())
 }
 
-pub fn extend_from_slice(bytes: &mut Vec<u8>, extension: &[u8]) -> (usize, usize) {
-    let begin = bytes.len();
-    bytes.extend_from_slice(extension);
-    (begin, bytes.len())
+pub fn write_command(bytes: &mut Vec<u8>, name: &str) {
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.extend_from_slice(LINE_SEPARATOR);
 }
 
-pub fn extend_name_value(
+/// Writes the header line for the name and value, escaping both as required, and
+/// returns the ranges of the name and the value in the buffer.
+pub fn write_encoded_header<N: Display + ?Sized, V: Display + ?Sized>(
     bytes: &mut Vec<u8>,
-    name: &str,
-    value: &mut Vec<u8>,
+    name: &N,
+    value: &V,
 ) -> ((usize, usize), (usize, usize)) {
-    let name_range = extend_from_slice(bytes, name.as_bytes());
-    extend_from_slice(bytes, HEADER_PARTS_SEPARATOR);
-    let value_range = extend_from_vec(bytes, value);
+    let name_range = write_encoded(bytes, name);
+    bytes.extend_from_slice(HEADER_PARTS_SEPARATOR);
+    let value_range = write_encoded(bytes, value);
+    bytes.extend_from_slice(LINE_SEPARATOR);
 
     (name_range, value_range)
 }
 
-pub fn extend_name_value_line(
-    bytes: &mut Vec<u8>,
-    name: &str,
-    value: &mut Vec<u8>,
-) -> ((usize, usize), (usize, usize)) {
-    let result = extend_name_value(bytes, name, value);
-    extend_from_slice(bytes, LINE_SEPARATOR);
-    result
+/// The number of bytes `write_encoded_header` writes for the name and value.
+pub fn encoded_header_len<N: Display + ?Sized, V: Display + ?Sized>(name: &N, value: &V) -> usize {
+    encoded_len(name) + HEADER_PARTS_SEPARATOR.len() + encoded_len(value) + LINE_SEPARATOR.len()
 }
 
-pub fn write_command(bytes: &mut Vec<u8>, name: &str) {
-    bytes.extend_from_slice(name.as_bytes());
-    bytes.extend_from_slice(LINE_SEPARATOR);
+/// Writes the displayed value to the buffer, escaping as required for a header name or value,
+/// and returns the range it occupies.
+///
+/// # Panics
+///
+/// If the `Display` implementation of the value returns an error, as `ToString::to_string` does. Writing to the buffer
+/// never fails, so only an implementation which fails on its own, contrary to the contract of `Display`, can panic.
+pub fn write_encoded<V: Display + ?Sized>(bytes: &mut Vec<u8>, value: &V) -> (usize, usize) {
+    let begin = bytes.len();
+    write!(EncodingWriter { bytes }, "{}", value).expect("Header value could not be written");
+    (begin, bytes.len())
 }
 
-pub fn write_header(
-    bytes: &mut Vec<u8>,
-    header_name: &str,
-    value_bytes: &mut Vec<u8>,
-) -> ((usize, usize), (usize, usize)) {
-    extend_name_value_line(bytes, header_name, value_bytes)
+/// The number of bytes `write_encoded` writes for the value.
+///
+/// # Panics
+///
+/// As `write_encoded`, if the `Display` implementation of the value returns an error.
+pub fn encoded_len<V: Display + ?Sized>(value: &V) -> usize {
+    let mut counter = EncodedLength(0);
+    write!(counter, "{}", value).expect("Header value could not be written");
+    counter.0
+}
+
+/// Displays as the decoded text a typed header value was read from, if that text still reads as the value, and
+/// otherwise as the value, so that a frame which is rebuilt is written with the spelling it was read with.
+pub struct Spelling<'a, V> {
+    value: &'a V,
+    text: Option<&'a str>,
+}
+
+impl<'a, V: FromStr + PartialEq> Spelling<'a, V> {
+    /// The value of the header with the name, spelt as in `spellings`, which pairs header names with their text.
+    pub fn new(spellings: &'a [(&'static str, String)], name: &str, value: &'a V) -> Self {
+        let text = spellings
+            .iter()
+            .find(|(spelt, _)| *spelt == name)
+            .map(|(_, text)| text.as_str())
+            .filter(|text| V::from_str(text).ok().as_ref() == Some(value));
+
+        Spelling { value, text }
+    }
+
+    /// Whether the value is displayed as the text it was read from.
+    pub fn is_spelt(&self) -> bool {
+        self.text.is_some()
+    }
+}
+
+impl<'a, V: Display> Display for Spelling<'a, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.text {
+            Some(text) => f.write_str(text),
+            None => self.value.fmt(f),
+        }
+    }
+}
+
+/// Escapes everything written to it into the underlying buffer.
+struct EncodingWriter<'a> {
+    bytes: &'a mut Vec<u8>,
+}
+
+impl<'a> Write for EncodingWriter<'a> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let mut start = 0;
+        // All the characters which need escaping are single bytes
+        for (index, byte) in s.bytes().enumerate() {
+            if let Some(escaped) = encode_char(byte as char) {
+                self.bytes.extend_from_slice(&s.as_bytes()[start..index]);
+                self.bytes.extend_from_slice(escaped.as_bytes());
+                start = index + 1;
+            }
+        }
+        self.bytes.extend_from_slice(&s.as_bytes()[start..]);
+        Ok(())
+    }
+}
+
+/// Counts the bytes which would be written by `EncodingWriter`.
+struct EncodedLength(usize);
+
+impl Write for EncodedLength {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0 += s.len()
+            + s.bytes()
+                .filter(|byte| encode_char(*byte as char).is_some())
+                .count();
+        Ok(())
+    }
 }
 
 pub fn write_headers_end(bytes: &mut Vec<u8>) {
@@ -63,6 +142,27 @@ pub fn write_frame_end(bytes: &mut Vec<u8>) {
     bytes.extend_from_slice(TERMINATOR);
 }
 
+/// The slice of `to` at the position at which `slice` is in `from`, for re-pointing the slices of a frame at a copy
+/// of its buffer. A slice which is not in `from`, such as a default value, is returned unchanged.
+pub fn rebase<'a>(slice: &'a [u8], from: &[u8], to: &'a [u8]) -> &'a [u8] {
+    let offset = (slice.as_ptr() as usize).wrapping_sub(from.as_ptr() as usize);
+
+    if offset <= from.len() && slice.len() <= from.len() - offset {
+        &to[offset..offset + slice.len()]
+    } else {
+        slice
+    }
+}
+
+/// As [`rebase`], for strings.
+///
+/// # Safety
+///
+/// `to` must be byte-identical to `from`, so that the slice of `to` is valid UTF-8, as the string in `from` is.
+pub unsafe fn rebase_str<'a>(slice: &'a str, from: &[u8], to: &'a [u8]) -> &'a str {
+    std::str::from_utf8_unchecked(rebase(slice.as_bytes(), from, to))
+}
+
 pub fn decode_escape_sequence(slice: &str) -> Result<char, StompParseError> {
     match slice {
         "\\\\" => Ok('\\'),
@@ -76,6 +176,23 @@ pub fn decode_escape_sequence(slice: &str) -> Result<char, StompParseError> {
     }
 }
 
+/// Checks, without allocating, that every backslash in the raw text starts a valid escape sequence, so that
+/// `decode_str` cannot fail on it.
+pub fn check_escapes(raw: &str) -> Result<&str, StompParseError> {
+    let mut bytes = raw.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'\\' && !matches!(bytes.next(), Some(b'r' | b'n' | b'c' | b'\\')) {
+            return Err(StompParseError::new(format!(
+                "Invalid escape sequence in: {}",
+                raw
+            )));
+        }
+    }
+
+    Ok(raw)
+}
+
 pub fn decode_str(raw: &str) -> Result<Either<&str, String>, StompParseError> {
     match raw.find('\\') {
         None => Ok(Either::Left(raw)),
@@ -93,11 +210,24 @@ fn decode_at_and_continue(
     index: usize,
 ) -> Result<(), StompParseError> {
     buffer.push_str(&slice[..index]);
-    if index < slice.len() - 1 {
-        buffer.push(decode_escape_sequence(&slice[index..index + 2])?);
-        decode_slice(buffer, &slice[index + 2..])
-    } else {
-        Err(StompParseError::new("input ends with control character \\"))
+    match slice[index + 1..].chars().next() {
+        Some(escaped) => {
+            // The escaped character may be multi-byte, so the sequence must end on its boundary
+            let end = index + 1 + escaped.len_utf8();
+            buffer.push(decode_escape_sequence(&slice[index..end])?);
+            decode_slice(buffer, &slice[end..])
+        }
+        None => Err(StompParseError::new("input ends with control character \\")),
+    }
+}
+
+fn encode_char(c: char) -> Option<&'static str> {
+    match c {
+        '\\' => Some("\\\\"),
+        '\r' => Some("\\r"),
+        '\n' => Some("\\n"),
+        ':' => Some("\\c"),
+        _ => None,
     }
 }
 
@@ -113,7 +243,6 @@ fn decode_slice(buffer: &mut String, slice: &str) -> Result<(), StompParseError>
 
 #[cfg(test)]
 mod test {
-    use crate::common::constants::*;
     use crate::common::functions::*;
 
     #[test]
@@ -126,12 +255,29 @@ mod test {
     }
 
     #[test]
-    pub fn write_header_appends_and_separates() {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.append(&mut vec![1u8, 2, 3]);
-        write_header(&mut bytes, "FUNK", &mut b"hello".to_vec());
+    pub fn check_escapes_accepts_only_decodable_text() {
+        for valid in ["", "abc", "a\\\\b", "\\r\\n\\c"] {
+            assert_eq!(Ok(valid), check_escapes(valid).map_err(|_| ()));
+            assert!(decode_str(valid).is_ok());
+        }
+        for invalid in ["\\", "a\\t", "\\\\\\", "\\é"] {
+            assert!(check_escapes(invalid).is_err());
+            assert!(decode_str(invalid).is_err());
+        }
+    }
+
+    struct Unprintable;
+
+    impl Display for Unprintable {
+        fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            Err(std::fmt::Error)
+        }
+    }
 
-        assert_eq!(bytes.as_slice(), b"\x01\x02\x03FUNK:hello\n");
+    #[test]
+    #[should_panic(expected = "Header value could not be written")]
+    pub fn write_encoded_panics_when_value_fails_to_display() {
+        write_encoded(&mut Vec::new(), &Unprintable);
     }
 
     #[test]
@@ -153,75 +299,70 @@ mod test {
     }
 
     #[test]
-    pub fn extend_from_vec_consumes() {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.append(&mut vec![1, 2, 3]);
-
-        let mut extension = vec![1, 2, 3, 4];
+    pub fn decode_str_rejects_escaped_multibyte_char() {
+        assert!(decode_str("a\\\u{e9}").is_err());
+    }
 
-        let range = extend_from_vec(&mut bytes, &mut extension);
+    #[test]
+    pub fn decode_str_continues_after_multibyte_char() {
+        let decoded = decode_str("\u{e9}\\c\u{e9}").expect("Decode failed");
 
-        assert_eq!(bytes, vec![1, 2, 3, 1, 2, 3, 4]);
-        assert_eq!(extension, vec![]);
-        assert_eq!((3, 7), range);
+        assert_eq!("\u{e9}:\u{e9}", &*decoded);
     }
 
     #[test]
-    pub fn extend_from_slice_extends() {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.append(&mut vec![1, 2, 3]);
+    pub fn write_encoded_escapes_and_decodes() {
+        let value = "a:b\\c\nd\re";
+        let mut bytes: Vec<u8> = vec![1u8, 2, 3];
 
-        let extension = vec![1, 2, 3, 4];
+        let range = write_encoded(&mut bytes, value);
 
-        let range = extend_from_slice(&mut bytes, &extension);
+        let encoded = std::str::from_utf8(&bytes[range.0..range.1]).unwrap();
 
-        assert_eq!(bytes, vec![1, 2, 3, 1, 2, 3, 4]);
-        assert_eq!(extension, vec![1, 2, 3, 4]);
-        assert_eq!((3, 7), range);
+        assert_eq!((3, 3 + encoded_len(value)), range);
+        assert_eq!("a\\cb\\\\c\\nd\\re", encoded);
+        assert_eq!(value, &*decode_str(encoded).unwrap());
     }
 
     #[test]
-    pub fn extend_name_value_extends() {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.append(&mut vec![1, 2, 3]);
-
-        let mut extension = vec![1, 2, 3, 4];
-
-        let name = "foobar";
-
-        let (name_range, value_range) = extend_name_value(&mut bytes, name, &mut extension);
+    pub fn spelling_is_kept_only_while_it_reads_as_the_value() {
+        let spellings = [("content-length", "007".to_owned())];
+
+        assert_eq!(
+            "007",
+            Spelling::new(&spellings, "content-length", &7u32).to_string()
+        );
+        assert_eq!(
+            "8",
+            Spelling::new(&spellings, "content-length", &8u32).to_string()
+        );
+        assert_eq!("7", Spelling::new(&spellings, "receipt", &7u32).to_string());
+        assert!(!Spelling::new(&[], "content-length", &7u32).is_spelt());
+    }
 
-        let name_length = name.as_bytes().len();
+    #[test]
+    pub fn write_encoded_header_appends_and_separates() {
+        let mut bytes: Vec<u8> = vec![1u8, 2, 3];
 
-        let expected_value_begin = 3 + name_length + HEADER_PARTS_SEPARATOR.len();
-        let expected_value_end = expected_value_begin + 4;
+        let (name_range, value_range) = write_encoded_header(&mut bytes, "FU:NK", &42);
 
-        assert_eq!(extension, vec![]);
-        assert_eq!((3, 3 + name_length), name_range);
-        assert_eq!((expected_value_begin, expected_value_end), value_range);
-        assert_eq!(expected_value_end, bytes.len());
+        assert_eq!(bytes.as_slice(), b"\x01\x02\x03FU\\cNK:42\n");
+        assert_eq!((3, 9), name_range);
+        assert_eq!((10, 12), value_range);
+        assert_eq!(bytes.len() - 3, encoded_header_len("FU:NK", &42));
     }
 
     #[test]
-    pub fn extend_name_value_line_extends() {
+    pub fn extend_from_vec_consumes() {
         let mut bytes: Vec<u8> = Vec::new();
         bytes.append(&mut vec![1, 2, 3]);
 
         let mut extension = vec![1, 2, 3, 4];
 
-        let name = "foobar";
-
-        let (name_range, value_range) = extend_name_value_line(&mut bytes, name, &mut extension);
-
-        let name_length = name.as_bytes().len();
-
-        let expected_value_begin = 3 + name_length + HEADER_PARTS_SEPARATOR.len();
-        let expected_value_end = expected_value_begin + 4;
+        let range = extend_from_vec(&mut bytes, &mut extension);
 
-        assert_eq!(extension, vec![]);
-        assert_eq!((3, 3 + name_length), name_range);
-        assert_eq!((expected_value_begin, expected_value_end), value_range);
-        assert_eq!(&bytes[expected_value_end..], LINE_SEPARATOR);
-        assert_eq!(expected_value_end + LINE_SEPARATOR.len(), bytes.len());
+        assert_eq!(bytes, vec![1, 2, 3, 1, 2, 3, 4]);
+        assert!(extension.is_empty());
+        assert_eq!((3, 7), range);
     }
 }
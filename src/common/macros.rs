@@ -1,12 +1,25 @@
 #![doc(hidden)]
 /// This macro is useful for forcing repeat expression - particularly optional
 /// items - without actually outputting anything depending on the input.
-macro_rules! blank {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_blank {
     ($in:ident) => {};
 }
 
+/// Outputs the type in place of the input, for repeating a type once per item of an optional or repeated input.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_replace_with_type {
+    ($in:ident, $type:ty) => {
+        $type
+    };
+}
+
 /// Outputs true if an input is present, otherwise false.
-macro_rules! true_if_present {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_true_if_present {
     ($in:ident) => {
         true
     };
@@ -17,7 +30,9 @@ macro_rules! true_if_present {
 }
 
 /// Outputs the first token tree if present, otherwise the second.
-macro_rules! or_else {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_or_else {
     ($present:tt,$absent:tt) => {
         $present
     };
@@ -28,7 +43,9 @@ macro_rules! or_else {
 }
 
 /// Outputs the first type if present, otherwise the second.
-macro_rules! or_else_type {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_or_else_type {
     ($present:ty,$default:ty) => {
         $present
     };
@@ -39,7 +56,9 @@ macro_rules! or_else_type {
 }
 
 /// If the first token-tree is present it chooses the second one as output; if not, the third
-macro_rules! choose_from_presence {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_choose_from_presence {
     ($in:tt $present:tt, $absent:tt) => {
         $present
     };
@@ -49,10 +68,30 @@ macro_rules! choose_from_presence {
     };
 }
 
-macro_rules! if_not_present {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_if_not_present {
     ($in:tt ($absent:item)) => {};
 
     (($absent:item)) => {
         $absent
     };
 }
+
+/// Outputs the items only if this crate is built with the `arbitrary` feature, irrespective of the features of the
+/// crate in which the macro is expanded.
+#[cfg(feature = "arbitrary")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_if_arbitrary {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "arbitrary"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_if_arbitrary {
+    ($($item:item)*) => {};
+}
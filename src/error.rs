@@ -3,6 +3,8 @@ use std::convert::Into;
 
 use nom::error::{ContextError, FromExternalError, ParseError, VerboseError};
 
+use crate::body::Charset;
+
 #[derive(Debug)]
 pub struct StompParseError {
     message: String,
@@ -29,3 +31,73 @@ impl From<std::io::Error> for StompParseError {
 pub trait FullError<I, E>: ParseError<I> + FromExternalError<I, E> + ContextError<I> {}
 
 impl<I, E> FullError<I, E> for VerboseError<I> {}
+
+/// The reasons the body of a frame cannot be decoded as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyDecodeError {
+    /// The `content-type` header is not a valid media type.
+    InvalidContentType(String),
+    /// The `charset` parameter of the `content-type` header names an encoding which is not supported.
+    UnsupportedCharset(String),
+    /// The body is not valid in the encoding; `offset` is that of the first invalid byte.
+    InvalidEncoding { charset: Charset, offset: usize },
+}
+
+impl std::fmt::Display for BodyDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            BodyDecodeError::InvalidContentType(message) => f.write_str(message),
+            BodyDecodeError::UnsupportedCharset(charset) => {
+                write!(f, "Unsupported charset: '{}'", charset)
+            }
+            BodyDecodeError::InvalidEncoding { charset, offset } => {
+                write!(f, "Invalid {} at byte {} of the body", charset, offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BodyDecodeError {}
+
+impl From<BodyDecodeError> for StompParseError {
+    fn from(error: BodyDecodeError) -> Self {
+        StompParseError::new(error.to_string())
+    }
+}
+
+/// The reasons a typed value cannot be encoded as, or decoded from, the body of a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BodyCodecError {
+    /// The `content-type` header is missing, invalid, or not one the codec decodes.
+    ContentTypeMismatch {
+        expected: String,
+        found: Option<String>,
+    },
+    Encode(String),
+    Decode(String),
+}
+
+impl std::fmt::Display for BodyCodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            BodyCodecError::ContentTypeMismatch {
+                expected,
+                found: Some(found),
+            } => write!(f, "Expected content-type {}, found '{}'", expected, found),
+            BodyCodecError::ContentTypeMismatch {
+                expected,
+                found: None,
+            } => write!(f, "Expected content-type {}, found none", expected),
+            BodyCodecError::Encode(message) => write!(f, "Error encoding body: {}", message),
+            BodyCodecError::Decode(message) => write!(f, "Error decoding body: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for BodyCodecError {}
+
+impl From<BodyCodecError> for StompParseError {
+    fn from(error: BodyCodecError) -> Self {
+        StompParseError::new(error.to_string())
+    }
+}
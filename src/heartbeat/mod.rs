@@ -0,0 +1,277 @@
+//! Keeps a connection alive, and detects when it is dead, according to the heart-beats negotiated by its `CONNECT`
+//! and `CONNECTED` frames; see [`HeartBeatIntervals::negotiate`].
+//!
+//! [`HeartbeatMonitor`] performs no IO and reads no clock. It is told when the connection is read from or written to,
+//! and asked what to do at a given instant, so that it can be driven by any runtime, or by a mock clock in tests.
+//! With the `tokio` feature, `tokio::TokioHeartbeat` drives it with tokio's timers.
+//!
+//! ```
+//! use std::time::{Duration, Instant};
+//!
+//! use stomp_parser::headers::{HeartBeatIntervals, NegotiatedHeartbeat};
+//! use stomp_parser::heartbeat::{HeartbeatAction, HeartbeatMonitor};
+//!
+//! let start = Instant::now();
+//! let mut monitor = HeartbeatMonitor::new(
+//!     &HeartBeatIntervals::new(1000, 0),
+//!     &HeartBeatIntervals::new(0, 500),
+//!     NegotiatedHeartbeat::DEFAULT_GRACE,
+//!     start,
+//! );
+//!
+//! assert_eq!(Some(start + Duration::from_secs(1)), monitor.next_deadline());
+//! assert_eq!(None, monitor.poll(start + Duration::from_millis(999)));
+//! assert_eq!(
+//!     Some(HeartbeatAction::SendHeartbeat),
+//!     monitor.poll(start + Duration::from_secs(1))
+//! );
+//! ```
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+use std::time::Instant;
+
+use crate::client::ConnectFrame;
+use crate::headers::{HeartBeatIntervals, NegotiatedHeartbeat};
+use crate::server::ConnectedFrame;
+
+/// The heart-beat which is sent when the connection is otherwise idle: an end-of-line.
+pub const HEARTBEAT: &[u8] = b"\n";
+
+/// What must be done for the connection, once a deadline of a [`HeartbeatMonitor`] has passed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatAction {
+    /// Nothing has been written for a heart-beat period, so a [`HEARTBEAT`] must be sent.
+    SendHeartbeat,
+    /// Nothing has been read from the other side for longer than it promised, so the connection should be considered
+    /// dead.
+    PeerTimedOut,
+}
+
+/// Tracks when a connection was last read from and written to, and so when a heart-beat must be sent, or the other
+/// side be considered dead. See the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatMonitor {
+    heartbeat: NegotiatedHeartbeat,
+    last_read: Instant,
+    last_write: Instant,
+}
+
+impl HeartbeatMonitor {
+    /// Monitors a connection whose `heart-beat` header, on this side, is `own`, and on the other side is `other`. The
+    /// connection is taken to have been read from and written to at `now`.
+    pub fn new(
+        own: &HeartBeatIntervals,
+        other: &HeartBeatIntervals,
+        grace: f64,
+        now: Instant,
+    ) -> Self {
+        HeartbeatMonitor::negotiated(own.negotiate(other, grace), now)
+    }
+
+    /// Monitors a connection whose heart-beats have already been negotiated.
+    pub fn negotiated(heartbeat: NegotiatedHeartbeat, now: Instant) -> Self {
+        HeartbeatMonitor {
+            heartbeat,
+            last_read: now,
+            last_write: now,
+        }
+    }
+
+    /// Monitors the connection of a client which sent `connect` and received `connected` at `now`.
+    pub fn client(
+        connect: &ConnectFrame,
+        connected: &ConnectedFrame,
+        grace: f64,
+        now: Instant,
+    ) -> Self {
+        let (connect, connected) = intervals(connect, connected);
+        HeartbeatMonitor::new(&connect, &connected, grace, now)
+    }
+
+    /// Monitors the connection of a server which received `connect` and sent `connected` at `now`.
+    pub fn server(
+        connect: &ConnectFrame,
+        connected: &ConnectedFrame,
+        grace: f64,
+        now: Instant,
+    ) -> Self {
+        let (connect, connected) = intervals(connect, connected);
+        HeartbeatMonitor::new(&connected, &connect, grace, now)
+    }
+
+    /// The negotiated heart-beats.
+    pub fn heartbeat(&self) -> &NegotiatedHeartbeat {
+        &self.heartbeat
+    }
+
+    /// Records that data, whether a frame or a heart-beat, was read from the connection at `now`.
+    pub fn on_read(&mut self, now: Instant) {
+        self.last_read = self.last_read.max(now);
+    }
+
+    /// Records that data, whether a frame or a heart-beat, was written to the connection at `now`.
+    pub fn on_write(&mut self, now: Instant) {
+        self.last_write = self.last_write.max(now);
+    }
+
+    /// What must be done at `now`, if anything. A timed-out peer takes precedence over sending a heart-beat. The
+    /// action is returned until it is resolved, so a heart-beat must be recorded with [`HeartbeatMonitor::on_write`]
+    /// once it is sent.
+    pub fn poll(&self, now: Instant) -> Option<HeartbeatAction> {
+        if self.read_deadline().is_some_and(|deadline| now >= deadline) {
+            Some(HeartbeatAction::PeerTimedOut)
+        } else if self
+            .write_deadline()
+            .is_some_and(|deadline| now >= deadline)
+        {
+            Some(HeartbeatAction::SendHeartbeat)
+        } else {
+            None
+        }
+    }
+
+    /// The instant at which [`HeartbeatMonitor::poll`] is next due to return an action, or `None` if heart-beats are
+    /// disabled in both directions.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match (self.read_deadline(), self.write_deadline()) {
+            (Some(read), Some(write)) => Some(read.min(write)),
+            (read, write) => read.or(write),
+        }
+    }
+
+    fn read_deadline(&self) -> Option<Instant> {
+        self.heartbeat
+            .expect_within
+            .map(|period| self.last_read + period)
+    }
+
+    fn write_deadline(&self) -> Option<Instant> {
+        self.heartbeat
+            .send_every
+            .map(|period| self.last_write + period)
+    }
+}
+
+/// The `heart-beat` headers of the frames, which default to `0,0` if absent.
+fn intervals(
+    connect: &ConnectFrame,
+    connected: &ConnectedFrame,
+) -> (HeartBeatIntervals, HeartBeatIntervals) {
+    (
+        connect.heartbeat().value().clone(),
+        connected
+            .heartbeat()
+            .map(|heartbeat| heartbeat.value().clone())
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ConnectFrame;
+    use crate::headers::StompVersion;
+    use crate::server::ConnectedFrame;
+    use std::time::Duration;
+
+    fn millis(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn sends_heartbeats_while_idle() {
+        let start = Instant::now();
+        let mut monitor = HeartbeatMonitor::negotiated(
+            NegotiatedHeartbeat {
+                send_every: Some(Duration::from_millis(100)),
+                expect_within: None,
+            },
+            start,
+        );
+
+        monitor.on_write(millis(start, 50));
+
+        assert_eq!(Some(millis(start, 150)), monitor.next_deadline());
+        assert_eq!(None, monitor.poll(millis(start, 149)));
+        assert_eq!(
+            Some(HeartbeatAction::SendHeartbeat),
+            monitor.poll(millis(start, 150))
+        );
+
+        monitor.on_write(millis(start, 150));
+
+        assert_eq!(None, monitor.poll(millis(start, 150)));
+        assert_eq!(Some(millis(start, 250)), monitor.next_deadline());
+    }
+
+    #[test]
+    fn detects_silent_peers() {
+        let start = Instant::now();
+        let mut monitor = HeartbeatMonitor::negotiated(
+            NegotiatedHeartbeat {
+                send_every: Some(Duration::from_millis(100)),
+                expect_within: Some(Duration::from_millis(150)),
+            },
+            start,
+        );
+
+        monitor.on_read(millis(start, 100));
+        monitor.on_write(millis(start, 100));
+        // Late records do not move the deadlines back
+        monitor.on_read(millis(start, 10));
+
+        assert_eq!(Some(millis(start, 200)), monitor.next_deadline());
+        assert_eq!(
+            Some(HeartbeatAction::PeerTimedOut),
+            monitor.poll(millis(start, 250))
+        );
+    }
+
+    #[test]
+    fn disabled_heartbeats_have_no_deadline() {
+        let start = Instant::now();
+        let monitor = HeartbeatMonitor::negotiated(NegotiatedHeartbeat::default(), start);
+
+        assert_eq!(None, monitor.next_deadline());
+        assert_eq!(None, monitor.poll(millis(start, u64::from(u32::MAX))));
+    }
+
+    #[test]
+    fn client_and_server_take_opposite_sides() {
+        let start = Instant::now();
+        let connect = ConnectFrame::builder()
+            .host("b".to_owned())
+            .accept_version("1.2".parse().unwrap())
+            .heartbeat(HeartBeatIntervals::new(1000, 2000))
+            .build();
+        let connected = ConnectedFrame::builder()
+            .version(StompVersion::V1_2)
+            .heartbeat(HeartBeatIntervals::new(3000, 500))
+            .build();
+
+        assert_eq!(
+            &NegotiatedHeartbeat {
+                send_every: Some(Duration::from_millis(1000)),
+                expect_within: Some(Duration::from_millis(3000)),
+            },
+            HeartbeatMonitor::client(&connect, &connected, 1.0, start).heartbeat()
+        );
+        assert_eq!(
+            &NegotiatedHeartbeat {
+                send_every: Some(Duration::from_millis(3000)),
+                expect_within: Some(Duration::from_millis(1000)),
+            },
+            HeartbeatMonitor::server(&connect, &connected, 1.0, start).heartbeat()
+        );
+
+        let connected = ConnectedFrame::builder()
+            .version(StompVersion::V1_2)
+            .build();
+
+        assert_eq!(
+            None,
+            HeartbeatMonitor::client(&connect, &connected, 1.0, start).next_deadline()
+        );
+    }
+}
@@ -0,0 +1,102 @@
+//! Drives a [`HeartbeatMonitor`] with tokio's timers. Since it uses tokio's clock, it can be tested with paused time.
+//!
+//! ```no_run
+//! # async fn run(mut heartbeat: stomp_parser::heartbeat::tokio::TokioHeartbeat) {
+//! use stomp_parser::heartbeat::HeartbeatAction;
+//!
+//! loop {
+//!     tokio::select! {
+//!         // A frame, or a heart-beat, is read: heartbeat.on_read()
+//!         // A frame is written: heartbeat.on_write()
+//!         action = heartbeat.next_action() => match action {
+//!             HeartbeatAction::SendHeartbeat => {
+//!                 // Write stomp_parser::heartbeat::HEARTBEAT, then:
+//!                 heartbeat.on_write();
+//!             }
+//!             HeartbeatAction::PeerTimedOut => break,
+//!         }
+//!     }
+//! }
+//! # }
+//! ```
+use ::tokio::time::{sleep_until, Instant};
+
+use super::{HeartbeatAction, HeartbeatMonitor};
+
+/// A [`HeartbeatMonitor`] which reads tokio's clock, and waits for its deadlines with tokio's timers.
+#[derive(Debug, Clone)]
+pub struct TokioHeartbeat {
+    monitor: HeartbeatMonitor,
+}
+
+impl TokioHeartbeat {
+    /// Drives the monitor, which should have been created at tokio's current instant.
+    pub fn new(monitor: HeartbeatMonitor) -> Self {
+        TokioHeartbeat { monitor }
+    }
+
+    /// The monitor being driven.
+    pub fn monitor(&self) -> &HeartbeatMonitor {
+        &self.monitor
+    }
+
+    /// Records that data was read from the connection now.
+    pub fn on_read(&mut self) {
+        self.monitor.on_read(Instant::now().into_std());
+    }
+
+    /// Records that data was written to the connection now.
+    pub fn on_write(&mut self) {
+        self.monitor.on_write(Instant::now().into_std());
+    }
+
+    /// Waits until an action is due, and returns it; if heart-beats are disabled in both directions, it never
+    /// returns. It is cancel safe, so it can be used in `tokio::select!`.
+    pub async fn next_action(&self) -> HeartbeatAction {
+        loop {
+            if let Some(action) = self.monitor.poll(Instant::now().into_std()) {
+                return action;
+            }
+
+            match self.monitor.next_deadline() {
+                Some(deadline) => sleep_until(Instant::from_std(deadline)).await,
+                None => std::future::pending().await,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::headers::NegotiatedHeartbeat;
+    use std::time::Duration;
+
+    #[::tokio::test(start_paused = true)]
+    async fn waits_for_deadlines() {
+        let start = Instant::now();
+        let mut heartbeat = TokioHeartbeat::new(HeartbeatMonitor::negotiated(
+            NegotiatedHeartbeat {
+                send_every: Some(Duration::from_millis(100)),
+                expect_within: Some(Duration::from_millis(250)),
+            },
+            start.into_std(),
+        ));
+
+        assert_eq!(
+            HeartbeatAction::SendHeartbeat,
+            heartbeat.next_action().await
+        );
+        assert_eq!(Duration::from_millis(100), start.elapsed());
+
+        heartbeat.on_write();
+        assert_eq!(
+            HeartbeatAction::SendHeartbeat,
+            heartbeat.next_action().await
+        );
+        heartbeat.on_write();
+
+        assert_eq!(HeartbeatAction::PeerTimedOut, heartbeat.next_action().await);
+        assert_eq!(Duration::from_millis(250), start.elapsed());
+    }
+}
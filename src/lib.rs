@@ -28,9 +28,61 @@
 #[macro_use]
 mod common;
 pub mod error;
+pub mod heartbeat;
 mod model;
 mod parser;
+pub mod selector;
 
+pub use model::any;
+pub use model::body;
 pub use model::client;
+pub use model::destination;
+pub use model::frame;
 pub use model::headers;
+pub use model::raw;
 pub use model::server;
+pub use model::typestate;
+pub use parser::ContentLengthPolicy;
+
+/// The items which the code generated by [`frames!`] and [`header!`] refers to. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::common::constants;
+    pub use crate::common::functions;
+    pub use crate::parser::headers::{splice_header, HeaderLines};
+    pub use crate::parser::{command_line, null, remaining_without_null};
+    #[cfg(feature = "arbitrary")]
+    pub use arbitrary;
+    pub use either::Either;
+    pub use nom::error::VerboseError;
+    pub use paste;
+
+    /// Seals [`Frame`](crate::frame::Frame), which only the frames of this crate and those declared with
+    /// [`frames!`](crate::frames) implement.
+    pub trait Sealed {}
+}
+
+/// Entry points into the parser internals for the fuzz targets in `fuzz/`.
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub mod fuzzing {
+    use std::convert::TryFrom;
+
+    use crate::client::ClientFrame;
+    use crate::server::ServerFrame;
+
+    pub use crate::common::functions::decode_str;
+
+    /// Parses the input as the header section, and any body, of a frame with each of the client and server commands,
+    /// so that the headers are dispatched by the same code as when frames are parsed.
+    pub fn parse_headers(input: &[u8]) {
+        let frame = |command: &str| [command.as_bytes(), b"\n", input].concat();
+
+        for command in ClientFrame::COMMANDS {
+            let _ = ClientFrame::try_from(frame(command));
+        }
+        for command in ServerFrame::COMMANDS {
+            let _ = ServerFrame::try_from(frame(command));
+        }
+    }
+}
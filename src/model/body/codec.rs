@@ -0,0 +1,364 @@
+use super::MediaType;
+use crate::common::functions::decode_str;
+use crate::error::BodyCodecError;
+use crate::headers::ContentTypeValue;
+
+/// Encodes values of type `T` as bodies with a particular content type, and decodes them again.
+///
+/// The codecs for JSON, MessagePack and protobuf are enabled by the `json`, `msgpack` and `protobuf` features.
+pub trait BodyCodec<T> {
+    /// The content type of the bodies the codec encodes.
+    fn media_type(&self) -> MediaType;
+
+    /// Whether the codec decodes bodies with the content type. By default, those with the type and subtype of
+    /// [`BodyCodec::media_type`].
+    fn accepts(&self, media_type: &MediaType) -> bool {
+        let own = self.media_type();
+        own.type_() == media_type.type_() && own.subtype() == media_type.subtype()
+    }
+
+    fn encode(&self, value: &T) -> Result<Vec<u8>, BodyCodecError>;
+
+    fn decode(&self, body: &[u8]) -> Result<T, BodyCodecError>;
+}
+
+#[cfg(any(feature = "msgpack", feature = "protobuf"))]
+fn is_application(media_type: &MediaType, subtypes: &[&str]) -> bool {
+    media_type.type_() == "application" && subtypes.contains(&media_type.subtype())
+}
+
+/// Encodes values as JSON, with content type `application/json`. Also decodes the `+json` types, such as
+/// `application/problem+json`.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json")]
+impl JsonCodec {
+    fn accepts_type(media_type: &MediaType) -> bool {
+        media_type.type_() == "application"
+            && (media_type.subtype() == "json" || media_type.subtype().ends_with("+json"))
+    }
+
+    pub(crate) fn encode_value<T: serde::Serialize + ?Sized>(
+        value: &T,
+    ) -> Result<Vec<u8>, BodyCodecError> {
+        serde_json::to_vec(value).map_err(|error| BodyCodecError::Encode(error.to_string()))
+    }
+
+    fn decode_value<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, BodyCodecError> {
+        serde_json::from_slice(body).map_err(|error| BodyCodecError::Decode(error.to_string()))
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> BodyCodec<T> for JsonCodec {
+    fn media_type(&self) -> MediaType {
+        MediaType::new("application", "json")
+    }
+
+    fn accepts(&self, media_type: &MediaType) -> bool {
+        JsonCodec::accepts_type(media_type)
+    }
+
+    fn encode(&self, value: &T) -> Result<Vec<u8>, BodyCodecError> {
+        JsonCodec::encode_value(value)
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<T, BodyCodecError> {
+        JsonCodec::decode_value(body)
+    }
+}
+
+/// Encodes values as MessagePack, with structs as maps, and content type `application/msgpack`. Also decodes
+/// `application/x-msgpack` and `application/vnd.msgpack`.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl MessagePackCodec {
+    fn accepts_type(media_type: &MediaType) -> bool {
+        is_application(media_type, &["msgpack", "x-msgpack", "vnd.msgpack"])
+    }
+
+    fn decode_value<T: serde::de::DeserializeOwned>(body: &[u8]) -> Result<T, BodyCodecError> {
+        rmp_serde::from_slice(body).map_err(|error| BodyCodecError::Decode(error.to_string()))
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> BodyCodec<T> for MessagePackCodec {
+    fn media_type(&self) -> MediaType {
+        MediaType::new("application", "msgpack")
+    }
+
+    fn accepts(&self, media_type: &MediaType) -> bool {
+        MessagePackCodec::accepts_type(media_type)
+    }
+
+    fn encode(&self, value: &T) -> Result<Vec<u8>, BodyCodecError> {
+        rmp_serde::to_vec_named(value).map_err(|error| BodyCodecError::Encode(error.to_string()))
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<T, BodyCodecError> {
+        MessagePackCodec::decode_value(body)
+    }
+}
+
+/// Encodes protobuf messages, with content type `application/x-protobuf`. Also decodes `application/protobuf` and
+/// `application/vnd.google.protobuf`.
+#[cfg(feature = "protobuf")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProtobufCodec;
+
+#[cfg(feature = "protobuf")]
+impl<T: prost::Message + Default> BodyCodec<T> for ProtobufCodec {
+    fn media_type(&self) -> MediaType {
+        MediaType::new("application", "x-protobuf")
+    }
+
+    fn accepts(&self, media_type: &MediaType) -> bool {
+        is_application(
+            media_type,
+            &["x-protobuf", "protobuf", "vnd.google.protobuf"],
+        )
+    }
+
+    fn encode(&self, value: &T) -> Result<Vec<u8>, BodyCodecError> {
+        Ok(value.encode_to_vec())
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<T, BodyCodecError> {
+        T::decode(body).map_err(|error| BodyCodecError::Decode(error.to_string()))
+    }
+}
+
+/// Whether the content type is present, valid, and accepted by the codec.
+fn accepted<T, C: BodyCodec<T> + ?Sized>(
+    codec: &C,
+    content_type: Option<&ContentTypeValue>,
+) -> bool {
+    content_type
+        .and_then(|content_type| content_type.media_type().ok())
+        .is_some_and(|media_type| codec.accepts(&media_type))
+}
+
+fn mismatch(expected: String, content_type: Option<&ContentTypeValue>) -> BodyCodecError {
+    BodyCodecError::ContentTypeMismatch {
+        expected,
+        found: content_type.map(|content_type| {
+            decode_str(content_type.value())
+                .map(|decoded| decoded.to_string())
+                .unwrap_or_else(|_| content_type.value().to_owned())
+        }),
+    }
+}
+
+/// Decodes the body with the codec, if it accepts the content type.
+pub(crate) fn decode_body<T, C: BodyCodec<T> + ?Sized>(
+    codec: &C,
+    content_type: Option<&ContentTypeValue>,
+    body: &[u8],
+) -> Result<T, BodyCodecError> {
+    if accepted(codec, content_type) {
+        codec.decode(body)
+    } else {
+        Err(mismatch(codec.media_type().to_string(), content_type))
+    }
+}
+
+/// Decodes the body with whichever of the enabled serde codecs accepts the content type.
+#[cfg(any(feature = "json", feature = "msgpack"))]
+pub(crate) fn decode_serde_body<T: serde::de::DeserializeOwned>(
+    content_type: Option<&ContentTypeValue>,
+    body: &[u8],
+) -> Result<T, BodyCodecError> {
+    let media_type = content_type.and_then(|content_type| content_type.media_type().ok());
+
+    #[cfg(feature = "json")]
+    if media_type.as_ref().is_some_and(JsonCodec::accepts_type) {
+        return JsonCodec::decode_value(body);
+    }
+
+    #[cfg(feature = "msgpack")]
+    if media_type
+        .as_ref()
+        .is_some_and(MessagePackCodec::accepts_type)
+    {
+        return MessagePackCodec::decode_value(body);
+    }
+
+    let expected: &[&str] = &[
+        #[cfg(feature = "json")]
+        "application/json",
+        #[cfg(feature = "msgpack")]
+        "application/msgpack",
+    ];
+
+    Err(mismatch(expected.join(" or "), content_type))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::SendFrame;
+    use crate::server::MessageFrame;
+
+    /// Encodes and decodes bodies as UTF-8 text.
+    struct TextCodec;
+
+    impl BodyCodec<String> for TextCodec {
+        fn media_type(&self) -> MediaType {
+            MediaType::new("text", "plain").with_parameter("charset", "utf-8")
+        }
+
+        fn encode(&self, value: &String) -> Result<Vec<u8>, BodyCodecError> {
+            Ok(value.as_bytes().to_vec())
+        }
+
+        fn decode(&self, body: &[u8]) -> Result<String, BodyCodecError> {
+            String::from_utf8(body.to_vec())
+                .map_err(|error| BodyCodecError::Decode(error.to_string()))
+        }
+    }
+
+    fn message(content_type: Option<&str>, body: &[u8]) -> crate::server::MessageFrame<'static> {
+        let mut builder = MessageFrame::builder()
+            .message_id("1".to_owned())
+            .destination("a".to_owned())
+            .subscription("s".to_owned());
+        if let Some(content_type) = content_type {
+            builder = builder.content_type(content_type.to_owned());
+        }
+        builder.body(body.to_vec()).build()
+    }
+
+    #[test]
+    fn encoded_body_sets_content_headers() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .encoded_body(&TextCodec, &"héllo".to_owned())
+            .unwrap()
+            .build();
+
+        assert_eq!(
+            Some("text/plain;charset=utf-8"),
+            frame.content_type().map(|value| value.value())
+        );
+        assert_eq!(Some(&6), frame.content_length().map(|value| value.value()));
+        assert_eq!(Some("héllo".as_bytes()), frame.body());
+    }
+
+    #[test]
+    fn decode_body_with_checks_content_type() {
+        assert_eq!(
+            Ok("ok".to_owned()),
+            message(Some("TEXT/plain"), b"ok").decode_body_with(&TextCodec)
+        );
+        assert_eq!(
+            Err(BodyCodecError::ContentTypeMismatch {
+                expected: "text/plain;charset=utf-8".to_owned(),
+                found: Some("text/html".to_owned())
+            }),
+            message(Some("text/html"), b"ok").decode_body_with(&TextCodec)
+        );
+        assert_eq!(
+            Err(BodyCodecError::ContentTypeMismatch {
+                expected: "text/plain;charset=utf-8".to_owned(),
+                found: None
+            }),
+            message(None, b"ok").decode_body_with(&TextCodec)
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Order {
+        id: u32,
+        item: String,
+    }
+
+    #[cfg(feature = "json")]
+    fn order() -> Order {
+        Order {
+            id: 7,
+            item: "widget".to_owned(),
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_body_roundtrips() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .json_body(&order())
+            .unwrap()
+            .build();
+
+        assert_eq!(
+            Some("application/json"),
+            frame.content_type().map(|value| value.value())
+        );
+        assert_eq!(Some(&br#"{"id":7,"item":"widget"}"#[..]), frame.body());
+
+        let frame = message(
+            Some("application/json; charset=utf-8"),
+            frame.body().unwrap(),
+        );
+
+        assert_eq!(Ok(order()), frame.decode_body::<Order>());
+        assert!(matches!(
+            message(Some("application/json"), b"{").decode_body::<Order>(),
+            Err(BodyCodecError::Decode(_))
+        ));
+    }
+
+    #[cfg(all(feature = "json", feature = "msgpack"))]
+    #[test]
+    fn decode_body_picks_codec_from_content_type() {
+        let msgpack = BodyCodec::<Order>::encode(&MessagePackCodec, &order()).unwrap();
+
+        assert_eq!(
+            Ok(order()),
+            message(Some("application/x-msgpack"), &msgpack).decode_body::<Order>()
+        );
+        assert_eq!(
+            Err(BodyCodecError::ContentTypeMismatch {
+                expected: "application/json or application/msgpack".to_owned(),
+                found: Some("text/plain".to_owned())
+            }),
+            message(Some("text/plain"), b"{}").decode_body::<Order>()
+        );
+    }
+
+    #[cfg(feature = "protobuf")]
+    #[test]
+    fn protobuf_body_roundtrips() {
+        #[derive(Clone, PartialEq, prost::Message)]
+        struct Order {
+            #[prost(uint32, tag = "1")]
+            id: u32,
+            #[prost(string, tag = "2")]
+            item: String,
+        }
+
+        let order = Order {
+            id: 7,
+            item: "widget".to_owned(),
+        };
+
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .encoded_body(&ProtobufCodec, &order)
+            .unwrap()
+            .build();
+
+        let frame = message(
+            frame.content_type().map(|value| value.value()),
+            frame.body().unwrap(),
+        );
+
+        assert_eq!(Ok(order), frame.decode_body_with(&ProtobufCodec));
+    }
+}
@@ -0,0 +1,463 @@
+//! Implements the interpretation of frame bodies according to their `content-type` header.
+//!
+//! The [STOMP Protocol Specification,Version 1.2](https://stomp.github.io/stomp-specification-1.2.html#Header_content-type)
+//! specifies the content type of a body as a MIME type, whose `charset` parameter gives the encoding of text bodies.
+//! [`MediaType`] parses the value of the header, and [`Charset`] decodes text in the supported encodings. A
+//! [`BodyCodec`] encodes typed values as bodies and decodes them again.
+mod codec;
+
+pub(crate) use codec::decode_body;
+#[cfg(any(feature = "json", feature = "msgpack"))]
+pub(crate) use codec::decode_serde_body;
+pub use codec::BodyCodec;
+#[cfg(feature = "json")]
+pub use codec::JsonCodec;
+#[cfg(feature = "msgpack")]
+pub use codec::MessagePackCodec;
+#[cfg(feature = "protobuf")]
+pub use codec::ProtobufCodec;
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use nom::branch::alt;
+use nom::bytes::complete::take_while1;
+use nom::character::complete::{anychar, char, multispace0};
+use nom::combinator::{all_consuming, map, opt, verify};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, separated_pair, tuple};
+use nom::IResult;
+
+use crate::common::functions::decode_str;
+use crate::error::{BodyDecodeError, StompParseError};
+use crate::headers::ContentTypeValue;
+
+/// A media type, such as `text/plain;charset=utf-8`, as specified in
+/// [RFC 2045](https://datatracker.ietf.org/doc/html/rfc2045#section-5.1).
+///
+/// The type, subtype and parameter names are case-insensitive, and are held in lower case; parameter values are
+/// held as supplied, without quotes.
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    parameters: Vec<(String, String)>,
+}
+
+impl MediaType {
+    pub fn new(type_: &str, subtype: &str) -> MediaType {
+        MediaType {
+            type_: type_.to_ascii_lowercase(),
+            subtype: subtype.to_ascii_lowercase(),
+            parameters: Vec::new(),
+        }
+    }
+
+    /// Parses the (decoded) value of a `content-type` header.
+    pub fn parse(input: &str) -> Result<MediaType, StompParseError> {
+        media_type(input)
+            .map(|(_, media_type)| media_type)
+            .map_err(|_| StompParseError::new(format!("Invalid media type: '{}'", input)))
+    }
+
+    /// Adds the parameter, replacing any existing parameter with the same name.
+    pub fn with_parameter(mut self, name: &str, value: &str) -> MediaType {
+        let name = name.to_ascii_lowercase();
+        self.parameters.retain(|(existing, _)| *existing != name);
+        self.parameters.push((name, value.to_owned()));
+        self
+    }
+
+    /// The top-level type, such as `text` in `text/plain`.
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    /// The subtype, such as `plain` in `text/plain`.
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// The parameters, as name and value, in the order supplied.
+    pub fn parameters(&self) -> &[(String, String)] {
+        &self.parameters
+    }
+
+    /// The value of the parameter with the (case-insensitive) name.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(parameter, _)| parameter.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value of the `charset` parameter.
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = StompParseError;
+    fn from_str(input: &str) -> Result<MediaType, StompParseError> {
+        MediaType::parse(input)
+    }
+}
+
+/// Writes parameter values as quoted strings where they are not tokens.
+impl std::fmt::Display for MediaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.parameters {
+            if !value.is_empty() && value.chars().all(is_token_char) {
+                write!(f, ";{}={}", name, value)?;
+            } else {
+                write!(f, ";{}=\"", name)?;
+                for c in value.chars() {
+                    if c == '"' || c == '\\' {
+                        f.write_str("\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                f.write_str("\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+type Res<'a, T> = IResult<&'a str, T>;
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+fn token(input: &str) -> Res<'_, &str> {
+    take_while1(is_token_char)(input)
+}
+
+/// A quoted string, in which a backslash escapes the following character.
+fn quoted_string(input: &str) -> Res<'_, String> {
+    delimited(
+        char('"'),
+        map(
+            many0(alt((
+                preceded(char('\\'), anychar),
+                verify(anychar, |c| *c != '"' && *c != '\\'),
+            ))),
+            |chars| chars.into_iter().collect(),
+        ),
+        char('"'),
+    )(input)
+}
+
+fn parameter(input: &str) -> Res<'_, Option<(String, String)>> {
+    opt(separated_pair(
+        map(token, str::to_ascii_lowercase),
+        char('='),
+        alt((map(token, str::to_owned), quoted_string)),
+    ))(input)
+}
+
+/// Empty parameters, as in a trailing `;`, are ignored.
+fn media_type(input: &str) -> Res<'_, MediaType> {
+    map(
+        all_consuming(delimited(
+            multispace0,
+            pair(
+                separated_pair(token, char('/'), token),
+                many0(preceded(
+                    tuple((multispace0, char(';'), multispace0)),
+                    parameter,
+                )),
+            ),
+            multispace0,
+        )),
+        |((type_, subtype), parameters)| MediaType {
+            parameters: parameters.into_iter().flatten().collect(),
+            ..MediaType::new(type_, subtype)
+        },
+    )(input)
+}
+
+/// The text encodings which bodies can be decoded from.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+pub enum Charset {
+    Utf8,
+    /// UTF-16 with an optional byte order mark, which is skipped; big-endian if it has none.
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+    Iso8859_1,
+    UsAscii,
+}
+
+impl Charset {
+    /// The charset with the (case-insensitive) name or common alias, if it is supported.
+    pub fn from_name(name: &str) -> Option<Charset> {
+        match name.to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(Charset::Utf8),
+            "utf-16" | "utf16" => Some(Charset::Utf16),
+            "utf-16le" | "utf16le" => Some(Charset::Utf16Le),
+            "utf-16be" | "utf16be" => Some(Charset::Utf16Be),
+            "iso-8859-1" | "iso_8859-1" | "iso8859-1" | "latin1" | "l1" => Some(Charset::Iso8859_1),
+            "us-ascii" | "ascii" => Some(Charset::UsAscii),
+            _ => None,
+        }
+    }
+
+    /// The name registered with IANA.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Charset::Utf8 => "UTF-8",
+            Charset::Utf16 => "UTF-16",
+            Charset::Utf16Le => "UTF-16LE",
+            Charset::Utf16Be => "UTF-16BE",
+            Charset::Iso8859_1 => "ISO-8859-1",
+            Charset::UsAscii => "US-ASCII",
+        }
+    }
+
+    /// Decodes the bytes, borrowing them where they are already valid UTF-8.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, BodyDecodeError> {
+        let invalid = |offset| BodyDecodeError::InvalidEncoding {
+            charset: *self,
+            offset,
+        };
+
+        match self {
+            Charset::Utf8 => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|error| invalid(error.valid_up_to())),
+            Charset::Utf16 => match bytes {
+                [0xFE, 0xFF, ..] => decode_utf16(bytes, 2, u16::from_be_bytes, invalid),
+                [0xFF, 0xFE, ..] => decode_utf16(bytes, 2, u16::from_le_bytes, invalid),
+                _ => decode_utf16(bytes, 0, u16::from_be_bytes, invalid),
+            },
+            Charset::Utf16Le => decode_utf16(bytes, 0, u16::from_le_bytes, invalid),
+            Charset::Utf16Be => decode_utf16(bytes, 0, u16::from_be_bytes, invalid),
+            Charset::Iso8859_1 => Ok(match ascii(bytes) {
+                Ok(text) => Cow::Borrowed(text),
+                Err(_) => Cow::Owned(bytes.iter().copied().map(char::from).collect()),
+            }),
+            Charset::UsAscii => ascii(bytes).map(Cow::Borrowed).map_err(invalid),
+        }
+    }
+}
+
+impl FromStr for Charset {
+    type Err = BodyDecodeError;
+    fn from_str(name: &str) -> Result<Charset, BodyDecodeError> {
+        Charset::from_name(name).ok_or_else(|| BodyDecodeError::UnsupportedCharset(name.to_owned()))
+    }
+}
+
+impl std::fmt::Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        f.write_str(self.name())
+    }
+}
+
+/// The bytes as text if they are all ASCII, otherwise the offset of the first which is not.
+fn ascii(bytes: &[u8]) -> Result<&str, usize> {
+    match bytes.iter().position(|byte| !byte.is_ascii()) {
+        Some(offset) => Err(offset),
+        None => Ok(std::str::from_utf8(bytes).expect("ASCII is valid UTF-8")),
+    }
+}
+
+fn decode_utf16<'a>(
+    bytes: &[u8],
+    skip: usize,
+    unit: fn([u8; 2]) -> u16,
+    invalid: impl Fn(usize) -> BodyDecodeError,
+) -> Result<Cow<'a, str>, BodyDecodeError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(invalid(bytes.len() - 1));
+    }
+
+    let units = bytes[skip..]
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]));
+
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut offset = skip;
+    for c in char::decode_utf16(units) {
+        let c = c.map_err(|_| invalid(offset))?;
+        offset += 2 * c.len_utf16();
+        text.push(c);
+    }
+
+    Ok(Cow::Owned(text))
+}
+
+impl<'a> ContentTypeValue<'a> {
+    /// The content type, decoded and parsed as a media type.
+    pub fn media_type(&self) -> Result<MediaType, StompParseError> {
+        decode_str(self.value()).and_then(|content_type| MediaType::parse(&content_type))
+    }
+}
+
+/// Decodes the body according to the `charset` of the content type; UTF-8, the encoding the specification implies
+/// for `text/*` types, is assumed if there is no content type or it has no `charset`.
+pub(crate) fn text_body<'a>(
+    content_type: Option<&ContentTypeValue>,
+    body: &'a [u8],
+) -> Result<Cow<'a, str>, BodyDecodeError> {
+    let charset = match content_type {
+        None => Charset::Utf8,
+        Some(content_type) => content_type
+            .media_type()
+            .map_err(|error| BodyDecodeError::InvalidContentType(error.message().to_owned()))?
+            .charset()
+            .map_or(Ok(Charset::Utf8), Charset::from_str)?,
+    };
+
+    charset.decode(body)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::{ClientFrame, SendFrame};
+    use crate::server::{ErrorFrame, MessageFrame};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn parses_media_type_with_parameters() {
+        let media_type =
+            MediaType::parse("Text/Plain; charset=\"utf-16\" ;format=flowed;").unwrap();
+
+        assert_eq!("text", media_type.type_());
+        assert_eq!("plain", media_type.subtype());
+        assert_eq!(Some("utf-16"), media_type.charset());
+        assert_eq!(Some("flowed"), media_type.parameter("FORMAT"));
+        assert_eq!(2, media_type.parameters().len());
+    }
+
+    #[test]
+    fn media_type_display_quotes_where_needed() {
+        let media_type = MediaType::new("application", "json")
+            .with_parameter("charset", "utf-8")
+            .with_parameter("profile", "a \"b\"");
+
+        assert_eq!(
+            "application/json;charset=utf-8;profile=\"a \\\"b\\\"\"",
+            media_type.to_string()
+        );
+        assert_eq!(media_type, media_type.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn rejects_invalid_media_types() {
+        for invalid in [
+            "",
+            "text",
+            "text/",
+            "/plain",
+            "text/plain;charset",
+            "te xt/plain",
+        ] {
+            assert!(
+                MediaType::parse(invalid).is_err(),
+                "'{}' should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_supported_charsets() {
+        let cases: [(Charset, &[u8]); 7] = [
+            (Charset::Utf8, "héllo".as_bytes()),
+            (Charset::Utf16Le, b"h\x00\xe9\x00l\x00l\x00o\x00"),
+            (Charset::Utf16Be, b"\x00h\x00\xe9\x00l\x00l\x00o"),
+            (Charset::Utf16, b"\xff\xfeh\x00\xe9\x00l\x00l\x00o\x00"),
+            (Charset::Utf16, b"\xfe\xff\x00h\x00\xe9\x00l\x00l\x00o"),
+            (Charset::Utf16, b"\x00h\x00\xe9\x00l\x00l\x00o"),
+            (Charset::Iso8859_1, b"h\xe9llo"),
+        ];
+
+        for (charset, bytes) in cases.iter() {
+            assert_eq!("héllo", charset.decode(bytes).unwrap(), "{}", charset);
+        }
+
+        assert!(matches!(
+            Charset::UsAscii.decode(b"hello").unwrap(),
+            Cow::Borrowed("hello")
+        ));
+    }
+
+    #[test]
+    fn reports_offset_of_invalid_encoding() {
+        let cases: [(Charset, &[u8], usize); 5] = [
+            (Charset::Utf8, b"ab\xffc", 2),
+            (Charset::Utf16Le, b"a\x00b", 2),
+            (Charset::Utf16Le, b"a\x00\x00\xdcb\x00", 2),
+            (Charset::Utf16Be, b"\x00a\xd8\x00\x00b", 2),
+            (Charset::UsAscii, b"abc\x80", 3),
+        ];
+
+        for (charset, bytes, offset) in cases.iter() {
+            assert_eq!(
+                Err(BodyDecodeError::InvalidEncoding {
+                    charset: *charset,
+                    offset: *offset
+                }),
+                charset.decode(bytes),
+                "{}",
+                charset
+            );
+        }
+    }
+
+    #[test]
+    fn text_body_uses_charset_of_content_type() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .content_type("text/plain;charset=ISO-8859-1".to_owned())
+            .body(b"caf\xe9".to_vec())
+            .build();
+
+        assert_eq!("café", frame.text_body().unwrap());
+
+        let frame = MessageFrame::builder()
+            .message_id("1".to_owned())
+            .destination("a".to_owned())
+            .subscription("s".to_owned())
+            .content_type("text/plain;charset=utf-16le".to_owned())
+            .body(b"o\x00k\x00".to_vec())
+            .build();
+
+        assert_eq!("ok", frame.text_body().unwrap());
+
+        let frame = ErrorFrame::builder().body(b"ok".to_vec()).build();
+
+        assert_eq!("ok", frame.text_body().unwrap());
+    }
+
+    #[test]
+    fn text_body_reports_unsupported_charset_and_invalid_content_type() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .content_type("text/plain;charset=koi8-r".to_owned())
+            .build();
+
+        assert_eq!(
+            Err(BodyDecodeError::UnsupportedCharset("koi8-r".to_owned())),
+            frame.text_body()
+        );
+
+        let frame =
+            ClientFrame::try_from(b"SEND\ndestination:a\ncontent-type:text\n\n\x00".to_vec());
+
+        let Ok(ClientFrame::Send(frame)) = frame else {
+            panic!("Send Frame not parsed correctly");
+        };
+
+        assert!(matches!(
+            frame.text_body(),
+            Err(BodyDecodeError::InvalidContentType(_))
+        ));
+    }
+}
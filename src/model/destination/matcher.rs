@@ -0,0 +1,442 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::client::SubscribeFrame;
+use crate::error::StompParseError;
+use crate::headers::DecodableValue;
+
+/// The wildcard syntax a broker uses in subscription destinations.
+///
+/// Destinations are split into words at `separator`. Everything up to and including the last `/`,
+/// such as `/topic/`, is treated as a single literal word, so that the wildcards apply to the name
+/// of the destination only; this does not apply if the separator itself is `/`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WildcardDialect {
+    /// Separates the words of a destination.
+    pub separator: char,
+    /// A word which matches exactly one word.
+    pub any_word: &'static str,
+    /// A word which matches zero or more words.
+    pub any_words: &'static str,
+}
+
+impl WildcardDialect {
+    /// ActiveMQ: `*` matches a word and `>` any number of words, separated by `.`.
+    pub const ACTIVEMQ: WildcardDialect = WildcardDialect {
+        separator: '.',
+        any_word: "*",
+        any_words: ">",
+    };
+
+    /// RabbitMQ topic exchanges: `*` matches a word and `#` any number of words, separated by `.`.
+    pub const RABBITMQ: WildcardDialect = WildcardDialect {
+        separator: '.',
+        any_word: "*",
+        any_words: "#",
+    };
+
+    /// MQTT-style topics, as used for example by brokers bridging MQTT: `+` matches a word and `#`
+    /// any number of words, separated by `/`.
+    pub const MQTT: WildcardDialect = WildcardDialect {
+        separator: '/',
+        any_word: "+",
+        any_words: "#",
+    };
+
+    fn words<'a>(&self, destination: &'a str) -> Vec<&'a str> {
+        let (prefix, name) = match destination.rfind('/') {
+            Some(index) if self.separator != '/' => destination.split_at(index + 1),
+            _ => ("", destination),
+        };
+
+        Some(prefix)
+            .filter(|prefix| !prefix.is_empty())
+            .into_iter()
+            .chain(name.split(self.separator))
+            .collect()
+    }
+
+    /// The words of the pattern, with consecutive multi-word wildcards, which match the same as one, collapsed.
+    fn pattern<'a>(&self, pattern: &'a str) -> Vec<&'a str> {
+        let mut words = self.words(pattern);
+        words.dedup_by(|word, previous| *word == self.any_words && *previous == self.any_words);
+        words
+    }
+}
+
+struct Node<T> {
+    literals: HashMap<String, Node<T>>,
+    any_word: Option<Box<Node<T>>>,
+    any_words: Option<Box<Node<T>>>,
+    values: Vec<T>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Node {
+            literals: HashMap::new(),
+            any_word: None,
+            any_words: None,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<T> Node<T> {
+    fn is_empty(&self) -> bool {
+        self.values.is_empty()
+            && self.literals.is_empty()
+            && self.any_word.is_none()
+            && self.any_words.is_none()
+    }
+
+    /// Removes the value from the node for the pattern, pruning nodes left empty. Returns whether
+    /// it was found.
+    fn remove(&mut self, dialect: &WildcardDialect, pattern: &[&str], value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        fn remove_from<T: PartialEq>(
+            child: &mut Option<Box<Node<T>>>,
+            dialect: &WildcardDialect,
+            pattern: &[&str],
+            value: &T,
+        ) -> bool {
+            let removed = child
+                .as_mut()
+                .is_some_and(|node| node.remove(dialect, pattern, value));
+            if child.as_ref().is_some_and(|node| node.is_empty()) {
+                *child = None;
+            }
+            removed
+        }
+
+        match pattern.split_first() {
+            None => match self.values.iter().position(|candidate| candidate == value) {
+                Some(index) => {
+                    self.values.remove(index);
+                    true
+                }
+                None => false,
+            },
+            Some((word, rest)) if *word == dialect.any_word => {
+                remove_from(&mut self.any_word, dialect, rest, value)
+            }
+            Some((word, rest)) if *word == dialect.any_words => {
+                remove_from(&mut self.any_words, dialect, rest, value)
+            }
+            Some((word, rest)) => {
+                let removed = self
+                    .literals
+                    .get_mut(*word)
+                    .is_some_and(|node| node.remove(dialect, rest, value));
+                if self.literals.get(*word).is_some_and(|node| node.is_empty()) {
+                    self.literals.remove(*word);
+                }
+                removed
+            }
+        }
+    }
+}
+
+/// The nodes which the words of a destination read so far lead to, each once, along with whether each is reached
+/// through a multi-word wildcard, and so matches another word by staying where it is.
+struct States<'a, T> {
+    nodes: Vec<(&'a Node<T>, bool)>,
+    seen: HashSet<*const Node<T>>,
+}
+
+impl<'a, T> States<'a, T> {
+    fn new() -> Self {
+        States {
+            nodes: Vec::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Adds the node, unless already present, along with the multi-word wildcard following it, which matches no words.
+    fn enter(&mut self, node: &'a Node<T>, repeats: bool) {
+        if self.seen.insert(node) {
+            self.nodes.push((node, repeats));
+            if let Some(any_words) = &node.any_words {
+                self.enter(any_words, true);
+            }
+        }
+    }
+
+    /// The states after matching the word.
+    fn next(&self, word: &str) -> Self {
+        let mut next = States::new();
+        for &(node, repeats) in &self.nodes {
+            if repeats {
+                next.enter(node, true);
+            }
+            if let Some(literal) = node.literals.get(word) {
+                next.enter(literal, false);
+            }
+            if let Some(any_word) = &node.any_word {
+                next.enter(any_word, false);
+            }
+        }
+        next
+    }
+}
+
+/// An index of destination patterns, which may contain wildcards, and the values, such as
+/// subscription ids, registered for them. Patterns are stored in a trie of their words, which is
+/// walked as an automaton, visiting each node at most once per word of the destination. Finding the
+/// values whose patterns match a destination therefore takes time proportional to the number of
+/// words in the destination and the number of nodes the words lead to, however many wildcards the
+/// patterns contain, rather than to the number of patterns.
+///
+/// Patterns and destinations are matched in their decoded form.
+pub struct DestinationMatcher<T> {
+    dialect: WildcardDialect,
+    root: Node<T>,
+}
+
+impl<T> DestinationMatcher<T> {
+    /// An empty matcher for patterns of the dialect.
+    pub fn new(dialect: WildcardDialect) -> Self {
+        DestinationMatcher {
+            dialect,
+            root: Node::default(),
+        }
+    }
+
+    /// The dialect of the patterns this matcher accepts.
+    pub fn dialect(&self) -> &WildcardDialect {
+        &self.dialect
+    }
+
+    /// Whether no values are registered.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_empty()
+    }
+
+    /// Registers the value for the pattern.
+    pub fn insert(&mut self, pattern: &str, value: T) {
+        let dialect = self.dialect;
+        let node = dialect
+            .pattern(pattern)
+            .into_iter()
+            .fold(&mut self.root, |node, word| {
+                if word == dialect.any_word {
+                    node.any_word.get_or_insert_with(Box::default)
+                } else if word == dialect.any_words {
+                    node.any_words.get_or_insert_with(Box::default)
+                } else {
+                    node.literals.entry(word.to_owned()).or_default()
+                }
+            });
+        node.values.push(value);
+    }
+
+    /// Unregisters the value for the pattern, returning whether it was registered.
+    pub fn remove(&mut self, pattern: &str, value: &T) -> bool
+    where
+        T: PartialEq,
+    {
+        let words = self.dialect.pattern(pattern);
+        self.root.remove(&self.dialect, &words, value)
+    }
+
+    /// The values registered for patterns which match the destination, each distinct value once,
+    /// even if it is registered for several of them, in no particular order.
+    pub fn matches(&self, destination: &str) -> Vec<&T>
+    where
+        T: Eq + Hash,
+    {
+        let mut states = States::new();
+        states.enter(&self.root, false);
+        for word in self.dialect.words(destination) {
+            states = states.next(word);
+        }
+
+        let mut seen = HashSet::new();
+        states
+            .nodes
+            .iter()
+            .flat_map(|(node, _)| &node.values)
+            .filter(|value| seen.insert(*value))
+            .collect()
+    }
+}
+
+impl DestinationMatcher<String> {
+    /// Registers the id of the subscription for its destination.
+    pub fn subscribe(&mut self, frame: &SubscribeFrame) -> Result<(), StompParseError> {
+        let destination = frame.destination().decoded_value()?;
+        let id = frame.id().decoded_value()?;
+        self.insert(&destination, id.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::{ClientFrame, SubscribeFrame};
+    use std::convert::TryFrom;
+
+    fn check_conformance(dialect: WildcardDialect, cases: &[(&str, &str, bool)]) {
+        for (pattern, destination, expected) in cases {
+            let mut matcher = DestinationMatcher::new(dialect);
+            matcher.insert(pattern, ());
+
+            assert_eq!(
+                *expected,
+                !matcher.matches(destination).is_empty(),
+                "pattern '{}' against '{}'",
+                pattern,
+                destination
+            );
+        }
+    }
+
+    #[test]
+    fn activemq_conformance() {
+        check_conformance(
+            WildcardDialect::ACTIVEMQ,
+            &[
+                ("/topic/a.b", "/topic/a.b", true),
+                ("/topic/a.b", "/topic/a.c", false),
+                ("/topic/a.b", "/queue/a.b", false),
+                ("/topic/a.*", "/topic/a.b", true),
+                ("/topic/a.*", "/topic/a", false),
+                ("/topic/a.*", "/topic/a.b.c", false),
+                ("/topic/*.b", "/topic/a.b", true),
+                ("/topic/*", "/topic/a", true),
+                ("/topic/a.>", "/topic/a", true),
+                ("/topic/a.>", "/topic/a.b", true),
+                ("/topic/a.>", "/topic/a.b.c", true),
+                ("/topic/a.>", "/topic/b.c", false),
+                ("/topic/>", "/topic/a.b.c", true),
+                ("/topic/a.*.>", "/topic/a.b.c.d", true),
+                ("/topic/a.*.>", "/topic/a", false),
+                ("/topic/a.#", "/topic/a.b", false),
+                ("/topic/a.#", "/topic/a.#", true),
+            ],
+        );
+    }
+
+    #[test]
+    fn rabbitmq_conformance() {
+        check_conformance(
+            WildcardDialect::RABBITMQ,
+            &[
+                ("/topic/a.b", "/topic/a.b", true),
+                ("/topic/a.b", "/topic/a.b.c", false),
+                ("/topic/a.*", "/topic/a.b", true),
+                ("/topic/a.*", "/topic/a", false),
+                ("/topic/a.#", "/topic/a", true),
+                ("/topic/a.#", "/topic/a.b.c", true),
+                ("/topic/#.c", "/topic/a.b.c", true),
+                ("/topic/#.c", "/topic/c", true),
+                ("/topic/#.c", "/topic/a.b", false),
+                ("/topic/a.#.c", "/topic/a.c", true),
+                ("/topic/a.#.c", "/topic/a.b.b.c", true),
+                ("/topic/a.#.c", "/topic/a.b.d", false),
+                ("/topic/*.#", "/topic/a", true),
+                ("/topic/#", "/topic/", true),
+                ("/exchange/x/a.*", "/exchange/x/a.b", true),
+                ("/exchange/x/a.*", "/exchange/y/a.b", false),
+                ("/topic/a.>", "/topic/a.b", false),
+            ],
+        );
+    }
+
+    #[test]
+    fn mqtt_conformance() {
+        check_conformance(
+            WildcardDialect::MQTT,
+            &[
+                ("a/b", "a/b", true),
+                ("a/+", "a/b", true),
+                ("a/+", "a/b/c", false),
+                ("a/#", "a", true),
+                ("a/#", "a/b/c", true),
+                ("+/+/c", "a/b/c", true),
+                ("a.*", "a.b", false),
+            ],
+        );
+    }
+
+    #[test]
+    fn returns_every_matching_value_once() {
+        let mut matcher = DestinationMatcher::new(WildcardDialect::RABBITMQ);
+        matcher.insert("/topic/a.b", "exact");
+        matcher.insert("/topic/a.*", "word");
+        matcher.insert("/topic/#.#", "overlapping");
+        matcher.insert("/topic/c", "other");
+        matcher.insert("/topic/#", "twice");
+        matcher.insert("/topic/*.b", "twice");
+
+        let mut matches = matcher.matches("/topic/a.b");
+        matches.sort();
+
+        assert_eq!(vec![&"exact", &"overlapping", &"twice", &"word"], matches);
+    }
+
+    #[test]
+    fn matches_many_wildcards_quickly() {
+        let mut matcher = DestinationMatcher::new(WildcardDialect::RABBITMQ);
+        matcher.insert("/topic/#.#.#.#.#.#.#.#.#.#.z", "consecutive");
+        matcher.insert("/topic/#.a.#.a.#.a.#.a.#.a.#.a.#.a.#.a.#.z", "separated");
+        let destination = format!("/topic/{}", ["a"; 30].join("."));
+
+        let start = std::time::Instant::now();
+        let unmatched = matcher.matches(&destination);
+        let mut matched = matcher.matches(&format!("{}.z", destination));
+        matched.sort();
+
+        assert!(unmatched.is_empty());
+        assert_eq!(vec![&"consecutive", &"separated"], matched);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+
+        assert!(matcher.remove("/topic/#.#.z", &"consecutive"));
+    }
+
+    #[test]
+    fn remove_unregisters_value() {
+        let mut matcher = DestinationMatcher::new(WildcardDialect::ACTIVEMQ);
+        matcher.insert("/topic/a.>", 1);
+        matcher.insert("/topic/a.>", 2);
+
+        assert!(matcher.remove("/topic/a.>", &1));
+        assert!(!matcher.remove("/topic/a.>", &1));
+        assert_eq!(vec![&2], matcher.matches("/topic/a.b"));
+
+        assert!(matcher.remove("/topic/a.>", &2));
+        assert!(matcher.is_empty());
+    }
+
+    #[test]
+    fn subscribes_frames() {
+        let mut matcher = DestinationMatcher::new(WildcardDialect::ACTIVEMQ);
+
+        let frame = ClientFrame::try_from(
+            b"SUBSCRIBE\ndestination:/topic/prices.>\nid:sub\\c1\n\n\x00".to_vec(),
+        )
+        .unwrap();
+        let ClientFrame::Subscribe(frame) = frame else {
+            panic!("Subscribe Frame not parsed correctly");
+        };
+        matcher.subscribe(&frame).unwrap();
+
+        matcher
+            .subscribe(
+                &SubscribeFrame::builder()
+                    .destination("/topic/prices.*.eur".to_owned())
+                    .id("2".to_owned())
+                    .build(),
+            )
+            .unwrap();
+
+        let mut matches = matcher.matches("/topic/prices.gold.eur");
+        matches.sort();
+
+        assert_eq!(vec!["2", "sub:1"], matches);
+        assert_eq!(vec!["sub:1"], matcher.matches("/topic/prices.gold"));
+    }
+}
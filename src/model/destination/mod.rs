@@ -0,0 +1,374 @@
+//! Implements a structured model for the values of `destination` headers.
+//!
+//! The STOMP specification leaves the interpretation of destinations to the server, and each broker
+//! uses its own naming scheme. A [`DestinationScheme`] parses destinations into a [`Destination`]
+//! and renders them back; [`RabbitMq`] and [`ActiveMq`] implement the schemes of those brokers, and
+//! other schemes can be supplied by implementing the trait.
+//!
+//! A [`DestinationMatcher`] finds the subscriptions whose destinations, which may contain wildcards
+//! in one of several [`WildcardDialect`]s, match the destination of a message.
+mod matcher;
+
+pub use matcher::{DestinationMatcher, WildcardDialect};
+
+use crate::client::{SendFrameBuilder, SubscribeFrameBuilder};
+use crate::common::functions::decode_str;
+use crate::error::StompParseError;
+use crate::headers::DestinationValue;
+use crate::server::MessageFrameBuilder;
+use crate::typestate::{Set, Unset};
+
+/// A destination, as interpreted by a [`DestinationScheme`].
+#[derive(Eq, PartialEq, Debug, Clone, Hash)]
+pub enum Destination {
+    /// A queue, from which each message is delivered to a single subscriber.
+    Queue(String),
+    /// A queue which already exists, and which the broker does not create on use.
+    ExistingQueue(String),
+    /// A topic, from which each message is delivered to every subscriber.
+    Topic(String),
+    /// An exchange, to which messages are sent with an optional routing key.
+    Exchange {
+        exchange: String,
+        routing_key: Option<String>,
+    },
+    /// A temporary queue, which only exists for the lifetime of the connection.
+    TemporaryQueue(String),
+    /// A temporary topic, which only exists for the lifetime of the connection.
+    TemporaryTopic(String),
+    /// A destination which the scheme does not recognise, as it was supplied. It cannot be rendered if the scheme
+    /// would recognise it, as it would then be parsed as a different destination.
+    Other(String),
+}
+
+impl Destination {
+    /// Parses the (decoded) destination according to the scheme.
+    pub fn parse<S: DestinationScheme + ?Sized>(destination: &str, scheme: &S) -> Destination {
+        scheme.parse(destination)
+    }
+
+    /// Renders the destination according to the scheme, in the form builders accept it.
+    pub fn render<S: DestinationScheme + ?Sized>(
+        &self,
+        scheme: &S,
+    ) -> Result<String, StompParseError> {
+        scheme.render(self)
+    }
+}
+
+/// A broker's naming scheme for destinations.
+pub trait DestinationScheme {
+    /// Parses the (decoded) destination. Destinations which the scheme does not recognise are
+    /// returned as [`Destination::Other`].
+    fn parse(&self, destination: &str) -> Destination;
+
+    /// Renders the destination, or fails if the scheme has no way of expressing it. A [`Destination::Other`] which
+    /// the scheme recognises is rejected, so that what is rendered parses as the same destination.
+    fn render(&self, destination: &Destination) -> Result<String, StompParseError>;
+}
+
+/// Renders a destination which the scheme does not recognise as it is, unless the scheme would recognise it.
+fn render_other<S: DestinationScheme + ?Sized>(
+    scheme: &S,
+    name: &str,
+    destination: &str,
+) -> Result<String, StompParseError> {
+    match scheme.parse(destination) {
+        Destination::Other(_) => Ok(destination.to_owned()),
+        parsed => Err(StompParseError::new(format!(
+            "{:?} would be read as {:?} in the {} destination scheme",
+            destination, parsed, name
+        ))),
+    }
+}
+
+/// Splits off the prefix, if the remainder is not empty.
+fn strip<'a>(destination: &'a str, prefix: &str) -> Option<&'a str> {
+    destination
+        .strip_prefix(prefix)
+        .filter(|name| !name.is_empty())
+}
+
+fn unsupported(scheme: &str, destination: &Destination) -> StompParseError {
+    StompParseError::new(format!(
+        "{:?} cannot be expressed in the {} destination scheme",
+        destination, scheme
+    ))
+}
+
+/// The destinations of the [RabbitMQ STOMP plugin](https://www.rabbitmq.com/stomp.html#d):
+/// `/queue/`, `/amq/queue/`, `/topic/`, `/exchange/{exchange}[/{routing key}]` and `/temp-queue/`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RabbitMq;
+
+impl DestinationScheme for RabbitMq {
+    fn parse(&self, destination: &str) -> Destination {
+        if let Some(name) = strip(destination, "/queue/") {
+            Destination::Queue(name.to_owned())
+        } else if let Some(name) = strip(destination, "/amq/queue/") {
+            Destination::ExistingQueue(name.to_owned())
+        } else if let Some(name) = strip(destination, "/topic/") {
+            Destination::Topic(name.to_owned())
+        } else if let Some(exchange) = strip(destination, "/exchange/") {
+            match exchange.split_once('/') {
+                Some((exchange, routing_key)) if !exchange.is_empty() => Destination::Exchange {
+                    exchange: exchange.to_owned(),
+                    routing_key: Some(routing_key.to_owned()),
+                },
+                Some(_) => Destination::Other(destination.to_owned()),
+                None => Destination::Exchange {
+                    exchange: exchange.to_owned(),
+                    routing_key: None,
+                },
+            }
+        } else if let Some(name) = strip(destination, "/temp-queue/") {
+            Destination::TemporaryQueue(name.to_owned())
+        } else {
+            Destination::Other(destination.to_owned())
+        }
+    }
+
+    fn render(&self, destination: &Destination) -> Result<String, StompParseError> {
+        match destination {
+            Destination::Queue(name) => Ok(format!("/queue/{}", name)),
+            Destination::ExistingQueue(name) => Ok(format!("/amq/queue/{}", name)),
+            Destination::Topic(name) => Ok(format!("/topic/{}", name)),
+            Destination::Exchange {
+                exchange,
+                routing_key: None,
+            } => Ok(format!("/exchange/{}", exchange)),
+            Destination::Exchange {
+                exchange,
+                routing_key: Some(routing_key),
+            } => Ok(format!("/exchange/{}/{}", exchange, routing_key)),
+            Destination::TemporaryQueue(name) => Ok(format!("/temp-queue/{}", name)),
+            Destination::Other(destination) => render_other(self, "RabbitMQ", destination),
+            Destination::TemporaryTopic(_) => Err(unsupported("RabbitMQ", destination)),
+        }
+    }
+}
+
+/// The destinations of [ActiveMQ](https://activemq.apache.org/stomp): `/queue/`, `/topic/`,
+/// `/temp-queue/` and `/temp-topic/`. Names are hierarchical, with `.` as the separator.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ActiveMq;
+
+impl DestinationScheme for ActiveMq {
+    fn parse(&self, destination: &str) -> Destination {
+        if let Some(name) = strip(destination, "/queue/") {
+            Destination::Queue(name.to_owned())
+        } else if let Some(name) = strip(destination, "/topic/") {
+            Destination::Topic(name.to_owned())
+        } else if let Some(name) = strip(destination, "/temp-queue/") {
+            Destination::TemporaryQueue(name.to_owned())
+        } else if let Some(name) = strip(destination, "/temp-topic/") {
+            Destination::TemporaryTopic(name.to_owned())
+        } else {
+            Destination::Other(destination.to_owned())
+        }
+    }
+
+    fn render(&self, destination: &Destination) -> Result<String, StompParseError> {
+        match destination {
+            Destination::Queue(name) | Destination::ExistingQueue(name) => {
+                Ok(format!("/queue/{}", name))
+            }
+            Destination::Topic(name) => Ok(format!("/topic/{}", name)),
+            Destination::TemporaryQueue(name) => Ok(format!("/temp-queue/{}", name)),
+            Destination::TemporaryTopic(name) => Ok(format!("/temp-topic/{}", name)),
+            Destination::Other(destination) => render_other(self, "ActiveMQ", destination),
+            Destination::Exchange { .. } => Err(unsupported("ActiveMQ", destination)),
+        }
+    }
+}
+
+impl<'a> DestinationValue<'a> {
+    /// The destination, decoded and parsed according to the scheme.
+    pub fn destination<S: DestinationScheme + ?Sized>(
+        &self,
+        scheme: &S,
+    ) -> Result<Destination, StompParseError> {
+        decode_str(self.value()).map(|destination| scheme.parse(&destination))
+    }
+}
+
+impl SendFrameBuilder<Unset> {
+    /// Sets the `destination` header to the destination, rendered according to the scheme, or fails if the scheme
+    /// cannot render it; see [`Destination::render`].
+    pub fn structured_destination<S: DestinationScheme + ?Sized>(
+        self,
+        destination: &Destination,
+        scheme: &S,
+    ) -> Result<SendFrameBuilder<Set>, StompParseError> {
+        Ok(self.destination(destination.render(scheme)?))
+    }
+}
+
+impl<IdState> SubscribeFrameBuilder<Unset, IdState> {
+    /// As [`SendFrameBuilder::structured_destination`].
+    pub fn structured_destination<S: DestinationScheme + ?Sized>(
+        self,
+        destination: &Destination,
+        scheme: &S,
+    ) -> Result<SubscribeFrameBuilder<Set, IdState>, StompParseError> {
+        Ok(self.destination(destination.render(scheme)?))
+    }
+}
+
+impl<MessageIdState, SubscriptionState>
+    MessageFrameBuilder<MessageIdState, Unset, SubscriptionState>
+{
+    /// As [`SendFrameBuilder::structured_destination`].
+    pub fn structured_destination<S: DestinationScheme + ?Sized>(
+        self,
+        destination: &Destination,
+        scheme: &S,
+    ) -> Result<MessageFrameBuilder<MessageIdState, Set, SubscriptionState>, StompParseError> {
+        Ok(self.destination(destination.render(scheme)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::{ClientFrame, SendFrame};
+    use crate::headers::HeaderValue;
+    use std::convert::TryFrom;
+
+    fn exchange(exchange: &str, routing_key: Option<&str>) -> Destination {
+        Destination::Exchange {
+            exchange: exchange.to_owned(),
+            routing_key: routing_key.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn rabbitmq_destinations_roundtrip() {
+        let cases = [
+            ("/queue/a", Destination::Queue("a".to_owned())),
+            ("/amq/queue/a", Destination::ExistingQueue("a".to_owned())),
+            ("/topic/a.b", Destination::Topic("a.b".to_owned())),
+            ("/exchange/x", exchange("x", None)),
+            ("/exchange/x/", exchange("x", Some(""))),
+            ("/exchange/x/a/b", exchange("x", Some("a/b"))),
+            ("/temp-queue/a", Destination::TemporaryQueue("a".to_owned())),
+            ("/queue/", Destination::Other("/queue/".to_owned())),
+            (
+                "/exchange//a",
+                Destination::Other("/exchange//a".to_owned()),
+            ),
+            ("a", Destination::Other("a".to_owned())),
+        ];
+
+        for (raw, destination) in cases.iter() {
+            assert_eq!(*destination, RabbitMq.parse(raw), "parsing {}", raw);
+            assert_eq!(
+                *raw,
+                RabbitMq.render(destination).unwrap(),
+                "rendering {}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn activemq_destinations_roundtrip() {
+        let cases = [
+            ("/queue/a.b", Destination::Queue("a.b".to_owned())),
+            ("/topic/a.*", Destination::Topic("a.*".to_owned())),
+            ("/temp-queue/a", Destination::TemporaryQueue("a".to_owned())),
+            ("/temp-topic/a", Destination::TemporaryTopic("a".to_owned())),
+            ("/exchange/x", Destination::Other("/exchange/x".to_owned())),
+        ];
+
+        for (raw, destination) in cases.iter() {
+            assert_eq!(*destination, ActiveMq.parse(raw), "parsing {}", raw);
+            assert_eq!(
+                *raw,
+                ActiveMq.render(destination).unwrap(),
+                "rendering {}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn render_fails_when_scheme_lacks_destination() {
+        assert!(ActiveMq.render(&exchange("x", None)).is_err());
+        assert!(RabbitMq
+            .render(&Destination::TemporaryTopic("a".to_owned()))
+            .is_err());
+    }
+
+    #[test]
+    fn render_fails_for_other_destinations_the_scheme_recognises() {
+        let queue = Destination::Other("/queue/a".to_owned());
+
+        assert!(RabbitMq.render(&queue).is_err());
+        assert!(ActiveMq.render(&queue).is_err());
+        assert!(ActiveMq
+            .render(&Destination::Other("/temp-topic/a".to_owned()))
+            .is_err());
+        assert_eq!(
+            "/temp-topic/a",
+            RabbitMq
+                .render(&Destination::Other("/temp-topic/a".to_owned()))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn destination_value_parses_decoded_destination() {
+        let frame =
+            ClientFrame::try_from(b"SEND\ndestination:/exchange/x/a\\cb\n\n\x00".to_vec()).unwrap();
+
+        let ClientFrame::Send(frame) = frame else {
+            panic!("Send Frame not parsed correctly");
+        };
+
+        assert_eq!(
+            exchange("x", Some("a:b")),
+            frame.destination().destination(&RabbitMq).unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_accepts_rendered_destination() {
+        let destination = Destination::Topic("a.b".to_owned());
+
+        let frame = SendFrame::builder()
+            .destination(destination.render(&ActiveMq).unwrap())
+            .build();
+
+        assert_eq!("destination", frame.destination().header_name());
+        assert_eq!(
+            destination,
+            frame.destination().destination(&ActiveMq).unwrap()
+        );
+    }
+
+    #[test]
+    fn builder_sets_structured_destination() {
+        let destinations = [
+            exchange("x", Some("a:b")),
+            Destination::Queue("a".to_owned()),
+            Destination::Other("a/b".to_owned()),
+        ];
+
+        for destination in destinations.iter() {
+            let frame = SendFrame::builder()
+                .structured_destination(destination, &RabbitMq)
+                .unwrap()
+                .build();
+
+            assert_eq!(
+                *destination,
+                frame.destination().destination(&RabbitMq).unwrap()
+            );
+        }
+
+        assert!(SendFrame::builder()
+            .structured_destination(&Destination::Other("/topic/a".to_owned()), &RabbitMq)
+            .is_err());
+    }
+}
@@ -0,0 +1,226 @@
+//! Implements a frame model for tools which sit between client and server, such as proxies and recorders, and so
+//! must parse frames sent in either direction.
+//!
+//! ```
+//! use stomp_parser::any::{AnyFrame, Direction};
+//! use std::convert::TryFrom;
+//!
+//! let frame = AnyFrame::try_from(b"RECEIPT\nreceipt-id:r-1\n\n\x00".to_vec()).unwrap();
+//!
+//! assert_eq!(Direction::ServerToClient, frame.direction());
+//! assert_eq!("RECEIPT", frame.command());
+//! ```
+use std::convert::TryFrom;
+
+use super::raw::RawFrame;
+use crate::client::ClientFrame;
+use crate::error::StompParseError;
+use crate::frame::{display_frame, Frame};
+use crate::headers::ReceiptValue;
+use crate::parser::{command_line, ContentLengthPolicy};
+use crate::server::ServerFrame;
+use nom::error::VerboseError;
+
+/// The direction in which a frame is sent.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub enum Direction {
+    /// The frame is one a client sends.
+    ClientToServer,
+    /// The frame is one a server sends.
+    ServerToClient,
+}
+
+/// A frame sent in either direction. Parsing dispatches on the command, so the bytes are parsed only once.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AnyFrame {
+    Client(ClientFrame),
+    Server(ServerFrame),
+}
+
+impl AnyFrame {
+    /// Parses a frame as `try_from` does, but validates `content-length` headers according to the policy.
+    pub fn parse_with(
+        bytes: Vec<u8>,
+        policy: ContentLengthPolicy,
+    ) -> Result<Self, StompParseError> {
+        let (_, command) = command_line::<VerboseError<&[u8]>, StompParseError>(&bytes)
+            .map_err(|_| StompParseError::new("Error parsing frame"))?;
+        let command = std::str::from_utf8(command)
+            .map_err(|_| StompParseError::new("badly formed command string, not utf8"))?;
+
+        if ClientFrame::COMMANDS.contains(&command) {
+            ClientFrame::parse_with(bytes, policy).map(AnyFrame::Client)
+        } else if ServerFrame::COMMANDS.contains(&command) {
+            ServerFrame::parse_with(bytes, policy).map(AnyFrame::Server)
+        } else {
+            Err(StompParseError::new(format!("Unknown command {}", command)))
+        }
+    }
+
+    /// The direction in which the frame is sent.
+    pub fn direction(&self) -> Direction {
+        match self {
+            AnyFrame::Client(_) => Direction::ClientToServer,
+            AnyFrame::Server(_) => Direction::ServerToClient,
+        }
+    }
+
+    /// The command of the frame, as it occurs in the frame; that is, `STOMP` frames are reported as such, rather than
+    /// as `CONNECT`.
+    pub fn command(&self) -> &str {
+        Frame::command(self)
+    }
+
+    /// The names and values of the headers, in the order in which they occur and still escaped.
+    pub fn headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        Frame::headers(self).map(|header| (header.name(), header.value()))
+    }
+
+    /// The body, which is empty if the frame has none.
+    pub fn body(&self) -> &[u8] {
+        Frame::body(self)
+    }
+
+    /// The `receipt` header, by which the client requests a `RECEIPT` for the frame, if there is one. This is the
+    /// value the typed frame reports, for the frames which may request a receipt.
+    pub fn receipt(&self) -> Option<&ReceiptValue<'_>> {
+        match self {
+            AnyFrame::Client(ClientFrame::Abort(_)) => None,
+            AnyFrame::Client(ClientFrame::Ack(frame)) => frame.receipt(),
+            AnyFrame::Client(ClientFrame::Begin(frame)) => frame.receipt(),
+            AnyFrame::Client(ClientFrame::Commit(frame)) => frame.receipt(),
+            AnyFrame::Client(ClientFrame::Connect(_)) => None,
+            AnyFrame::Client(ClientFrame::Disconnect(frame)) => Some(frame.receipt()),
+            AnyFrame::Client(ClientFrame::Nack(frame)) => frame.receipt(),
+            AnyFrame::Client(ClientFrame::Send(frame)) => frame.receipt(),
+            AnyFrame::Client(ClientFrame::Subscribe(frame)) => frame.receipt(),
+            AnyFrame::Client(ClientFrame::Unsubscribe(frame)) => frame.receipt(),
+            AnyFrame::Server(_) => None,
+        }
+    }
+
+    /// The bytes of this frame. For a parsed frame, these are exactly the bytes it was parsed from.
+    pub fn raw_bytes(&self) -> &[u8] {
+        match self {
+            AnyFrame::Client(frame) => frame.raw_bytes(),
+            AnyFrame::Server(frame) => frame.raw_bytes(),
+        }
+    }
+}
+
+impl std::fmt::Display for AnyFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        display_frame(self, f)
+    }
+}
+
+impl crate::__private::Sealed for AnyFrame {}
+
+impl Frame for AnyFrame {
+    fn raw_bytes(&self) -> &[u8] {
+        self.raw_bytes()
+    }
+
+    fn into_raw(self) -> Vec<u8> {
+        self.into()
+    }
+}
+
+/// Parses an `AnyFrame` from the data contained in the provided vector of bytes.
+impl TryFrom<Vec<u8>> for AnyFrame {
+    type Error = StompParseError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, StompParseError> {
+        AnyFrame::parse_with(bytes, ContentLengthPolicy::default())
+    }
+}
+
+/// This implementation serialises [`AnyFrame`] into a byte array.
+impl From<AnyFrame> for Vec<u8> {
+    fn from(frame: AnyFrame) -> Vec<u8> {
+        match frame {
+            AnyFrame::Client(frame) => frame.into(),
+            AnyFrame::Server(frame) => frame.into(),
+        }
+    }
+}
+
+impl From<ClientFrame> for AnyFrame {
+    fn from(frame: ClientFrame) -> Self {
+        AnyFrame::Client(frame)
+    }
+}
+
+impl From<ServerFrame> for AnyFrame {
+    fn from(frame: ServerFrame) -> Self {
+        AnyFrame::Server(frame)
+    }
+}
+
+impl From<AnyFrame> for RawFrame {
+    fn from(frame: AnyFrame) -> Self {
+        match frame {
+            AnyFrame::Client(frame) => frame.into(),
+            AnyFrame::Server(frame) => frame.into(),
+        }
+    }
+}
+
+/// Parses the frame as an [`AnyFrame`], which fails if the command is not one the specification defines, or the
+/// headers or body do not suit it.
+impl TryFrom<RawFrame> for AnyFrame {
+    type Error = StompParseError;
+
+    fn try_from(frame: RawFrame) -> Result<Self, StompParseError> {
+        AnyFrame::try_from(Vec::from(frame))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn dispatches_on_the_command() {
+        let frame =
+            AnyFrame::try_from(b"STOMP\naccept-version:1.2\nhost:b\nreceipt:r-1\n\n\x00".to_vec())
+                .unwrap();
+
+        assert!(matches!(frame, AnyFrame::Client(ClientFrame::Connect(_))));
+        assert_eq!(Direction::ClientToServer, frame.direction());
+        assert_eq!("STOMP", frame.command());
+
+        let frame = AnyFrame::try_from(
+            b"MESSAGE\nmessage-id:m-1\ndestination:a\nsubscription:s-1\nreceipt:r-1\n\nbody\x00"
+                .to_vec(),
+        )
+        .unwrap();
+
+        assert!(matches!(frame, AnyFrame::Server(ServerFrame::Message(_))));
+        assert_eq!(Direction::ServerToClient, frame.direction());
+        assert_eq!(b"body", frame.body());
+        assert!(frame.receipt().is_none());
+
+        assert!(AnyFrame::try_from(b"NOTIFY\n\n\x00".to_vec()).is_err());
+        assert!(AnyFrame::try_from(b"SEND\n\n\x00".to_vec()).is_err());
+    }
+
+    #[test]
+    fn exposes_headers_and_receipt() {
+        let bytes = b"SEND\ndestination:a\\cb\nreceipt:r-1\nfoo:1\nfoo:2\n\nbody\x00".to_vec();
+        let frame = AnyFrame::try_from(bytes.clone()).unwrap();
+
+        assert_eq!(
+            vec![
+                ("destination", "a\\cb"),
+                ("receipt", "r-1"),
+                ("foo", "1"),
+                ("foo", "2")
+            ],
+            frame.headers().collect::<Vec<_>>()
+        );
+        assert_eq!("r-1", frame.receipt().unwrap().value());
+        assert_eq!(b"body", frame.body());
+        assert_eq!(bytes, Vec::from(frame));
+    }
+}
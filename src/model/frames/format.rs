@@ -0,0 +1,282 @@
+use std::fmt::{Debug, Display, Formatter, Result};
+
+use super::frame::Frame;
+
+/// The value shown in place of that of a redacted header.
+const REDACTED: &str = "<redacted>";
+
+/// The headers which are always redacted.
+const ALWAYS_REDACTED: &[&str] = &["passcode"];
+
+/// How a frame is formatted for people to read: the command, the headers with their values decoded, and a preview of
+/// the body, on one line or on several. Sensitive headers are redacted.
+///
+/// The `Display` implementations of the frames use the default format, or the multi-line one with `{:#}`. Their
+/// `Debug` implementations redact only the headers which are always redacted, such as `passcode`; a format's own
+/// redactions apply to `Debug` through [`FrameFormat::debug`].
+///
+/// ```
+/// use stomp_parser::client::ConnectFrame;
+/// use stomp_parser::frame::FrameFormat;
+///
+/// let frame = ConnectFrame::builder()
+///     .host("broker".to_owned())
+///     .accept_version("1.2".parse().unwrap())
+///     .login("guest".to_owned())
+///     .passcode("secret".to_owned())
+///     .add_custom_header("token".to_owned(), "abc".to_owned())
+///     .build();
+///
+/// assert_eq!(
+///     "CONNECT host:broker accept-version:1.2 heart-beat:0,0 login:guest passcode:<redacted> token:<redacted> \
+///     (empty body)",
+///     FrameFormat::default().redact("token").display(&frame).to_string()
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameFormat {
+    multi_line: bool,
+    redacted: Vec<String>,
+    body_preview: usize,
+}
+
+impl Default for FrameFormat {
+    /// Formats frames on one line, with a preview of at most 32 bytes of the body.
+    fn default() -> Self {
+        FrameFormat {
+            multi_line: false,
+            redacted: Vec::new(),
+            body_preview: 32,
+        }
+    }
+}
+
+impl FrameFormat {
+    /// Whether to put the command, each header and the body on lines of their own.
+    pub fn multi_line(mut self, multi_line: bool) -> Self {
+        self.multi_line = multi_line;
+        self
+    }
+
+    /// Redacts the header, in addition to `passcode`, which is always redacted. Names are compared ignoring ASCII case,
+    /// so that, for example, `authorization` also redacts `Authorization`.
+    pub fn redact(mut self, name: &str) -> Self {
+        self.redacted.push(name.to_owned());
+        self
+    }
+
+    /// The number of bytes of the body to show; any further bytes are elided.
+    pub fn body_preview(mut self, bytes: usize) -> Self {
+        self.body_preview = bytes;
+        self
+    }
+
+    /// Formats the frame in this format.
+    pub fn display<'f, F: Frame + ?Sized>(&'f self, frame: &'f F) -> FrameDisplay<'f, F> {
+        FrameDisplay {
+            format: self,
+            frame,
+        }
+    }
+
+    /// Formats the frame as its `Debug` implementation does, but with the redactions of this format.
+    pub fn debug<'f, F: Frame + ?Sized>(&'f self, frame: &'f F) -> FrameDebug<'f, F> {
+        FrameDebug {
+            format: self,
+            frame,
+        }
+    }
+
+    fn redacts(&self, name: &str) -> bool {
+        ALWAYS_REDACTED
+            .iter()
+            .any(|other| other.eq_ignore_ascii_case(name))
+            || self
+                .redacted
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A frame, formatted in a [`FrameFormat`].
+pub struct FrameDisplay<'f, F: ?Sized> {
+    format: &'f FrameFormat,
+    frame: &'f F,
+}
+
+impl<'f, F: Frame + ?Sized> Display for FrameDisplay<'f, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let separator = if self.format.multi_line { "\n  " } else { " " };
+
+        f.write_str(self.frame.command())?;
+
+        for header in self.frame.headers() {
+            let name = header
+                .decoded_name()
+                .map_or_else(|_| header.name().to_owned(), |name| name.to_string());
+            f.write_str(separator)?;
+
+            // Names are escaped as values are, so that neither can break the line they are logged on
+            if self.format.redacts(&name) {
+                write!(f, "{}:{}", name.escape_debug(), REDACTED)?;
+            } else {
+                let value = header
+                    .decoded_value()
+                    .map_or_else(|_| header.value().to_owned(), |value| value.to_string());
+                write!(f, "{}:{}", name.escape_debug(), value.escape_debug())?;
+            }
+        }
+
+        f.write_str(separator)?;
+        write_body_preview(f, self.frame.body(), self.format.body_preview)
+    }
+}
+
+fn write_body_preview(f: &mut Formatter<'_>, body: &[u8], preview: usize) -> Result {
+    if body.is_empty() {
+        return f.write_str("(empty body)");
+    }
+
+    write!(f, "({} bytes) \"", body.len())?;
+    body.iter()
+        .take(preview)
+        .flat_map(|byte| std::ascii::escape_default(*byte))
+        .try_for_each(|escaped| write!(f, "{}", escaped as char))?;
+    f.write_str(if body.len() > preview { "\"..." } else { "\"" })
+}
+
+/// A frame, formatted by `Debug` with the redactions of a [`FrameFormat`]: its command, its headers in order with
+/// their values decoded, and the length of the body.
+pub struct FrameDebug<'f, F: ?Sized> {
+    format: &'f FrameFormat,
+    frame: &'f F,
+}
+
+impl<'f, F: Frame + ?Sized> Debug for FrameDebug<'f, F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut debug = f.debug_struct(self.frame.command());
+
+        for header in self.frame.headers() {
+            let name = header
+                .decoded_name()
+                .map_or_else(|_| header.name().to_owned(), |name| name.to_string());
+            let field = name.escape_debug().to_string();
+
+            if self.format.redacts(&name) {
+                debug.field(&field, &format_args!("{}", REDACTED));
+            } else {
+                match header.decoded_value() {
+                    Ok(value) => debug.field(&field, &&*value),
+                    Err(_) => debug.field(&field, &header.value()),
+                };
+            }
+        }
+
+        debug
+            .field("body", &format_args!("{} bytes", self.frame.body().len()))
+            .finish()
+    }
+}
+
+/// Writes the `Debug` representation of the frame, which is that of [`FrameFormat::debug`] for the default format.
+#[doc(hidden)]
+pub fn debug_frame<F: Frame + ?Sized>(frame: &F, f: &mut Formatter<'_>) -> Result {
+    FrameFormat::default().debug(frame).fmt(f)
+}
+
+/// Writes the `Display` representation of the frame, which is that of the default [`FrameFormat`], or of the
+/// multi-line one if the alternate flag is set.
+#[doc(hidden)]
+pub fn display_frame<F: Frame + ?Sized>(frame: &F, f: &mut Formatter<'_>) -> Result {
+    let format = FrameFormat::default().multi_line(f.alternate());
+    format.display(frame).fmt(f)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::{ClientFrame, ConnectFrame};
+    use crate::server::MessageFrame;
+    use std::convert::TryFrom;
+
+    fn connect() -> ClientFrame {
+        ClientFrame::Connect(
+            ConnectFrame::builder()
+                .host("b".to_owned())
+                .accept_version("1.2".parse().unwrap())
+                .login("guest".to_owned())
+                .passcode("secret".to_owned())
+                .add_custom_header("x-api-key".to_owned(), "k".to_owned())
+                .build(),
+        )
+    }
+
+    #[test]
+    fn debug_redacts_passcode_and_those_of_the_format() {
+        let frame = connect();
+
+        assert_eq!(
+            "CONNECT { host: \"b\", accept-version: \"1.2\", heart-beat: \"0,0\", login: \"guest\", passcode: <redacted>, \
+            x-api-key: \"k\", body: 0 bytes }",
+            format!("{:?}", frame)
+        );
+
+        let format = FrameFormat::default().redact("X-API-Key");
+
+        assert!(format!("{:?}", format.debug(&frame)).contains("x-api-key: <redacted>"));
+        assert!(!format!("{:?}", format.debug(&frame)).contains("secret"));
+        assert!(format
+            .display(&frame)
+            .to_string()
+            .contains("x-api-key:<redacted>"));
+        assert!(format!("{:?}", frame).contains("x-api-key: \"k\""));
+    }
+
+    #[test]
+    fn header_names_are_escaped() {
+        let frame = MessageFrame::builder()
+            .message_id("m-1".to_owned())
+            .destination("a".to_owned())
+            .subscription("s-1".to_owned())
+            .add_custom_header("x\nmessage-id".to_owned(), "forged".to_owned())
+            .build();
+
+        assert_eq!(
+            "MESSAGE message-id:m-1 destination:a subscription:s-1 x\\nmessage-id:forged (empty body)",
+            frame.to_string()
+        );
+        assert!(format!("{:?}", frame).contains("x\\nmessage-id: \"forged\""));
+    }
+
+    #[test]
+    fn display_decodes_headers_and_previews_body() {
+        let frame = MessageFrame::builder()
+            .message_id("m-1".to_owned())
+            .destination("a:b".to_owned())
+            .subscription("s-1".to_owned())
+            .add_custom_header("line".to_owned(), "1\n2".to_owned())
+            .body(b"hello\x00world".to_vec())
+            .build();
+
+        assert_eq!(
+            "MESSAGE message-id:m-1 destination:a:b subscription:s-1 content-length:11 line:1\\n2 \
+            (11 bytes) \"hello\\x00w\"...",
+            FrameFormat::default()
+                .body_preview(7)
+                .display(&frame)
+                .to_string()
+        );
+        assert_eq!(
+            "MESSAGE\n  message-id:m-1\n  destination:a:b\n  subscription:s-1\n  content-length:11\n  \
+            line:1\\n2\n  (11 bytes) \"hello\\x00world\"",
+            format!("{:#}", frame)
+        );
+    }
+
+    #[test]
+    fn frames_without_body_are_shown_as_such() {
+        let frame = ClientFrame::try_from(b"BEGIN\ntransaction:t-1\n\n\x00".to_vec()).unwrap();
+
+        assert_eq!("BEGIN transaction:t-1 (empty body)", frame.to_string());
+    }
+}
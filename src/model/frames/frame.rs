@@ -0,0 +1,284 @@
+//! Implements uniform access to the command, headers and body of any frame, so that code which handles frames
+//! generically, such as logging, authorisation or routing, can be written once.
+//!
+//! ```
+//! use stomp_parser::client::ClientFrame;
+//! use stomp_parser::frame::Frame;
+//! use std::convert::TryFrom;
+//!
+//! fn log<F: Frame>(frame: &F) -> String {
+//!     let headers: Vec<String> = frame
+//!         .headers()
+//!         .map(|header| format!("{}={}", header.name(), header.value()))
+//!         .collect();
+//!     format!("{} [{}] {} bytes", frame.command(), headers.join(", "), frame.body().len())
+//! }
+//!
+//! let frame = ClientFrame::try_from(b"SEND\ndestination:a\nfoo:bar\n\nbody\x00".to_vec()).unwrap();
+//!
+//! assert_eq!("SEND [destination=a, foo=bar] 4 bytes", log(&frame));
+//! ```
+use either::Either;
+
+pub use super::format::{debug_frame, display_frame, FrameDebug, FrameDisplay, FrameFormat};
+use std::borrow::Cow;
+use std::hash::{Hash, Hasher};
+
+use crate::common::functions::decode_str;
+use crate::error::StompParseError;
+use crate::parser::command_line;
+use crate::parser::headers::HeaderLines;
+use crate::parser::remaining_without_null;
+use nom::error::VerboseError;
+
+/// A frame, typed or not. All methods are provided, given the bytes of the frame, which are always valid.
+///
+/// The trait is sealed, as the provided methods rely on that: it is implemented by the frames of this crate, and by
+/// those declared with [`frames!`](crate::frames), but cannot otherwise be implemented.
+///
+/// ```compile_fail
+/// use stomp_parser::frame::Frame;
+///
+/// struct Bytes(Vec<u8>);
+///
+/// impl Frame for Bytes {
+///     fn raw_bytes(&self) -> &[u8] {
+///         &self.0
+///     }
+///
+///     fn into_raw(self) -> Vec<u8> {
+///         self.0
+///     }
+/// }
+/// ```
+pub trait Frame: crate::__private::Sealed {
+    /// The bytes of the frame.
+    fn raw_bytes(&self) -> &[u8];
+
+    /// The bytes of the frame, consuming it.
+    fn into_raw(self) -> Vec<u8>
+    where
+        Self: Sized;
+
+    /// The command of the frame, as it occurs in the frame.
+    fn command(&self) -> &str {
+        let (_, command) = command_line::<VerboseError<&[u8]>, StompParseError>(self.raw_bytes())
+            .expect("Should never fail, because a valid frame");
+        std::str::from_utf8(command).expect("Should never fail, because a valid frame")
+    }
+
+    /// The headers of the frame, in the order in which they occur, including repeated ones.
+    fn headers(&self) -> Headers<'_> {
+        let (headers, _) = command_line::<VerboseError<&[u8]>, StompParseError>(self.raw_bytes())
+            .expect("Should never fail, because a valid frame");
+
+        Headers {
+            lines: HeaderLines::new(headers),
+        }
+    }
+
+    /// The first header with the name, which, as the specification requires, is the one that applies when a header
+    /// is repeated.
+    fn header(&self, name: &str) -> Option<RawHeader<'_>> {
+        self.headers().find(|header| {
+            header.decoded_name().is_ok_and(|decoded| {
+                decoded.either(|decoded| decoded == name, |decoded| decoded == name)
+            })
+        })
+    }
+
+    /// The body, which is empty if the frame has none.
+    fn body(&self) -> &[u8] {
+        let mut headers = self.headers();
+        headers.by_ref().for_each(drop);
+
+        remaining_without_null::<VerboseError<&[u8]>>(headers.lines.remaining())
+            .map(|(_, body)| body)
+            .expect("Should never fail, because a valid frame")
+    }
+}
+
+/// The decoded name and value of the header.
+fn decoded(header: RawHeader<'_>) -> (Cow<'_, str>, Cow<'_, str>) {
+    fn decode(decoded: Result<Either<&str, String>, StompParseError>) -> Cow<'_, str> {
+        decoded
+            .expect("Should never fail, because a valid frame")
+            .either(Cow::Borrowed, Cow::Owned)
+    }
+
+    (
+        decode(header.decoded_name()),
+        decode(header.decoded_value()),
+    )
+}
+
+/// Whether the frames have the same command, as it occurs in the frame, the same headers, decoded and in order, and
+/// the same body; which is how frames, typed or raw, are compared. A `CONNECT` frame is therefore not equal to a
+/// `STOMP` frame, although both are parsed as a `ConnectFrame`.
+#[doc(hidden)]
+pub fn eq_frames<F: Frame + ?Sized, G: Frame + ?Sized>(frame: &F, other: &G) -> bool {
+    frame.command() == other.command()
+        && frame.body() == other.body()
+        && frame
+            .headers()
+            .map(decoded)
+            .eq(other.headers().map(decoded))
+}
+
+/// Hashes the command, the decoded headers and the body of the frame, consistently with [`eq_frames`].
+#[doc(hidden)]
+pub fn hash_frame<F: Frame + ?Sized, H: Hasher>(frame: &F, state: &mut H) {
+    frame.command().hash(state);
+    frame
+        .headers()
+        .map(decoded)
+        .for_each(|header| header.hash(state));
+    frame.body().hash(state);
+}
+
+/// A header of a frame, with its name and value as they are in the frame, that is, escaped.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+pub struct RawHeader<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl<'a> RawHeader<'a> {
+    /// The name, as it is in the frame.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The value, as it is in the frame.
+    pub fn value(&self) -> &'a str {
+        self.value
+    }
+
+    /// The name, with escape sequences decoded.
+    pub fn decoded_name(&self) -> Result<Either<&'a str, String>, StompParseError> {
+        decode_str(self.name)
+    }
+
+    /// The value, with escape sequences decoded.
+    pub fn decoded_value(&self) -> Result<Either<&'a str, String>, StompParseError> {
+        decode_str(self.value)
+    }
+}
+
+/// Iterates over the headers of a frame; see [`Frame::headers`].
+pub struct Headers<'a> {
+    lines: HeaderLines<'a>,
+}
+
+impl<'a> Iterator for Headers<'a> {
+    type Item = RawHeader<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next().map(|line| {
+            let (name, value) = line.expect("Should never fail, because a valid frame");
+            RawHeader { name, value }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::any::AnyFrame;
+    use crate::client::{ClientFrame, SendFrame};
+    use crate::raw::RawFrame;
+    use crate::server::ErrorFrame;
+    use std::convert::TryFrom;
+
+    fn describe<F: Frame>(frame: &F) -> (String, Vec<(String, String)>, Vec<u8>) {
+        (
+            frame.command().to_owned(),
+            frame
+                .headers()
+                .map(|header| {
+                    (
+                        header.decoded_name().unwrap().to_string(),
+                        header.decoded_value().unwrap().to_string(),
+                    )
+                })
+                .collect(),
+            frame.body().to_vec(),
+        )
+    }
+
+    #[test]
+    fn all_frames_are_described_alike() {
+        let bytes = b"SEND\r\ndestination:a\\cb\nfoo:1\nfoo:2\n\nbody\x00".to_vec();
+        let expected = (
+            "SEND".to_owned(),
+            vec![
+                ("destination".to_owned(), "a:b".to_owned()),
+                ("foo".to_owned(), "1".to_owned()),
+                ("foo".to_owned(), "2".to_owned()),
+            ],
+            b"body".to_vec(),
+        );
+
+        let frame = ClientFrame::try_from(bytes.clone()).unwrap();
+        assert_eq!(expected, describe(&frame));
+        if let ClientFrame::Send(frame) = &frame {
+            assert_eq!(expected, describe(frame));
+        }
+        assert_eq!(
+            expected,
+            describe(&RawFrame::try_from(bytes.clone()).unwrap())
+        );
+        assert_eq!(
+            expected,
+            describe(&AnyFrame::try_from(bytes.clone()).unwrap())
+        );
+
+        assert_eq!(bytes, frame.into_raw());
+    }
+
+    #[test]
+    fn header_finds_first_by_decoded_name() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .add_custom_header("x:y".to_owned(), "1".to_owned())
+            .add_custom_header("x:y".to_owned(), "2".to_owned())
+            .build();
+
+        let header = Frame::header(&frame, "x:y").unwrap();
+        assert_eq!("x\\cy", header.name());
+        assert_eq!("1", header.value());
+        assert!(Frame::header(&frame, "x").is_none());
+    }
+
+    #[test]
+    fn typed_and_generic_access_agree_on_repeated_headers() {
+        let bytes =
+            b"SEND\ndestination:first\nreceipt:r1\ndestination:second\nreceipt:r2\n\n\x00".to_vec();
+
+        let ClientFrame::Send(frame) = ClientFrame::try_from(bytes.clone()).unwrap() else {
+            panic!("Send Frame not parsed correctly");
+        };
+        assert_eq!("first", frame.destination().value());
+        assert_eq!("r1", frame.receipt().unwrap().value());
+        assert_eq!(
+            "first",
+            Frame::header(&frame, "destination").unwrap().value()
+        );
+
+        let raw = RawFrame::try_from(bytes.clone()).unwrap();
+        assert_eq!(&"first", raw.header("destination").unwrap().value());
+        assert_eq!(&"r1", raw.header("receipt").unwrap().value());
+
+        let any = AnyFrame::try_from(bytes).unwrap();
+        assert_eq!("r1", any.receipt().unwrap().value());
+        assert_eq!("first", Frame::header(&any, "destination").unwrap().value());
+    }
+
+    #[test]
+    fn frames_without_body_have_empty_body() {
+        let frame = ErrorFrame::from_message("oops");
+
+        assert_eq!("ERROR", Frame::command(&frame));
+        assert_eq!(b"", Frame::body(&frame));
+    }
+}
@@ -1,8 +1,10 @@
-macro_rules! frame {
-    ( $name:ident,  $($comment:literal,)? $command:ident, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt $(: $opt_header_default_comment:literal)?)?  ),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident])?  $(,$long_comment:literal)*) => {
-        paste::paste! {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_frame {
+    ( $name:ident,  $($comment:literal,)? $command:ident, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt $(: $opt_header_default_comment:literal)?)?  ),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident $(: $length_header:ident)?])?  $(,$long_comment:literal)*) => {
+        $crate::__private::paste::paste! {
 
-            sender_frame!($name,  $($comment,)? $command, $origin $(, $header_name : $header_type )* $(,( $(  $opt_header_name : $opt_header_type $(: $opt_header_default $(: $opt_header_default_comment )?)?  ),* ))? $(,[custom: $has_custom])? $(,[body: $has_body])?  $(,$long_comment)*);
+            $crate::__stomp_sender_frame!($name,  $($comment,)? $command, $origin $(, $header_name : $header_type )* $(,( $(  $opt_header_name : $opt_header_type $(: $opt_header_default $(: $opt_header_default_comment )?)?  ),* ))? $(,[custom: $has_custom])? $(,[body: $has_body $(: $length_header)?])?  $(,$long_comment)*);
 
             $(#[doc = ""$comment]
             #[doc = ""])?
@@ -13,22 +15,26 @@ macro_rules! frame {
                 raw: Vec<u8>,
             $(
                 #[doc = "The value of the `"$header_name"` header."]
-                $header_name: [<$header_type Value>]<'a>,
+                $header_name: $crate::headers::[<$header_type Value>]<'a>,
             )*
             $($(
                 #[doc = "The value of the `"$opt_header_name"` header."]
                 $($(#[doc = "Defaults to `"$opt_header_default_comment"` if not supplied."])?)?
-                $opt_header_name: choose_from_presence!($($opt_header_default)? ([<$opt_header_type Value>]<'a>),(Option<[<$opt_header_type Value>]<'a>>)),
+                // Either type is parenthesised, to pass it as a single token tree
+                #[allow(unused_parens)]
+                $opt_header_name: $crate::__stomp_choose_from_presence!($($opt_header_default)? ($crate::headers::[<$opt_header_type Value>]<'a>),(Option<$crate::headers::[<$opt_header_type Value>]<'a>>)),
             )*)?
             $(
                 #[allow(unused)]
                 $has_custom: (),
-                pub custom: Vec<CustomValue>,
+                pub custom: Vec<$crate::headers::CustomValue>,
             )?
             $(
                 #[allow(unused)]
                 $has_body: &'a [u8],
             )?
+                // Frames without headers or body do not otherwise use the lifetime
+                phantom: std::marker::PhantomData<&'a ()>,
         }
 
         impl <'a> $name<'a> {
@@ -36,22 +42,43 @@ macro_rules! frame {
         }
 
         impl <'a> $name<'a> {
+            #[doc = "The bytes of this frame. For a parsed frame, these are exactly the bytes it was parsed from, so"]
+            #[doc = "the frame can be forwarded byte-identically."]
+            pub fn raw_bytes(&self) -> &[u8] {
+                &self.raw
+            }
+
+            #[doc = "The number of bytes this frame serialises to."]
+            pub fn encoded_len(&self) -> usize {
+                self.raw.len()
+            }
+
+            #[doc = "A builder pre-filled with the headers and body of this frame."]
+            pub fn to_builder(&self) -> [<$name Builder>] {
+                self.into()
+            }
+
+            #[doc = "Appends the serialised frame to the buffer, without consuming the frame."]
+            pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.raw);
+            }
 
             fn init(raw: Vec<u8>) -> Self {
                 $name {
                     raw,
                      $(
-                $header_name: [<$header_type Value>]::default(),
+                $header_name: $crate::headers::[<$header_type Value>]::default(),
             )*
                     $($(
-                $opt_header_name: choose_from_presence!($(($opt_header_default))? ([<$opt_header_type Value>]::default()),None),
+                $opt_header_name: $crate::__stomp_choose_from_presence!($(($opt_header_default))? ($crate::headers::[<$opt_header_type Value>]::default()),None),
             )*)? $(
                  #[allow(unused)]
                 $has_custom: (),
                 custom: vec![],
             )? $(
-                $has_body: &EMPTY,
+                $has_body: &$crate::__private::constants::EMPTY,
             )?
+                phantom: std::marker::PhantomData,
             }
         }
                 $(
@@ -60,47 +87,131 @@ macro_rules! frame {
                 }
             )?
 
+            $(
+                #[doc = "The typed value of the first custom header with the name of `V`, if there is one."]
+                pub fn custom_header<V: $crate::headers::TypedHeaderValue<'a>>(&self) -> Result<Option<V>, $crate::error::StompParseError> {
+                    $crate::__stomp_blank!($has_custom);
+                    self.custom
+                        .iter()
+                        .find(|header| $crate::headers::HeaderValue::header_name(*header) == V::NAME)
+                        .map(|header| V::parse(header.value()))
+                        .transpose()
+                }
+            )?
+
             $(
                 #[doc = "The value of the `"$header_name"` header."]
-                pub fn $header_name(&'a self) -> &'a [<$header_type Value>]<'a> {
+                pub fn $header_name(&'a self) -> &'a $crate::headers::[<$header_type Value>]<'a> {
                     &self.$header_name
                 }
             )*
             $($(
                 #[doc = "The value of the `"$opt_header_name"` header."]
                 $($(#[doc = "Defaults to `"$opt_header_default_comment"` if not supplied."])?)?
-                pub fn $opt_header_name(&'a self) -> choose_from_presence!($($opt_header_default)? (&'a [<$opt_header_type Value>]<'a>),(Option<&'a [<$opt_header_type Value>]<'a>>)) {
-                    choose_from_presence!($($opt_header_default)? (&self.$opt_header_name),(self.$opt_header_name.as_ref()))
+                // Either type is parenthesised, to pass it as a single token tree
+                #[allow(unused_parens)]
+                pub fn $opt_header_name(&'a self) -> $crate::__stomp_choose_from_presence!($($opt_header_default)? (&'a $crate::headers::[<$opt_header_type Value>]<'a>),(Option<&'a $crate::headers::[<$opt_header_type Value>]<'a>>)) {
+                    $crate::__stomp_choose_from_presence!($($opt_header_default)? (&self.$opt_header_name),(self.$opt_header_name.as_ref()))
                 }
             )*)?
         }
 
-        #[doc = "This implementation serialises [`"$name"`] into a byte array."]
-        impl <'a> Into<Vec<u8>> for $name<'a> {
-            fn into(self) -> Vec<u8> {
+        impl $name<'static> {
+            #[doc = "A copy of the frame with the header `name` set to `value`, by splicing the header line into a copy"]
+            #[doc = "of the raw bytes of the frame, leaving all other bytes, including line terminators, untouched. The"]
+            #[doc = "first occurrence of the header, which is the one that applies, is replaced, or the header is added"]
+            #[doc = "after the command if it is not present. The resulting frame is parsed again, so it is an error"]
+            #[doc = "to set a header this frame does not allow, or an invalid value for a typed header; this frame is"]
+            #[doc = "left as it is, so that it can still be used when that happens."]
+            pub fn with_header(&self, name: &str, value: &str) -> Result<Self, $crate::error::StompParseError> {
+                let mut raw = self.raw.clone();
+                $crate::__private::splice_header(&mut raw, name, value)?;
+                parsers::[<$name:snake>](Self::init(raw), $crate::ContentLengthPolicy::default())
+            }
+        }
+
+        #[doc = "Clones the frame, with its headers and body pointing into the buffer of the clone."]
+        impl <'a> Clone for $name<'a> {
+            fn clone(&self) -> Self {
+                let raw = self.raw.clone();
+
+                let ptr : *const [u8] = raw.as_slice();
+                // The buffer does not move when the clone does, so its slices remain valid
+                #[allow(unused_variables)]
+                let slice = unsafe { ptr.as_ref().unwrap() };
+
+                // The values are rebased onto a clone of their own buffer, so it is byte-identical to it
+                $name {
+                    $(
+                        $header_name: unsafe { self.$header_name.rebased(&self.raw, slice) },
+                    )*
+                    $($(
+                        $opt_header_name: $crate::__stomp_choose_from_presence!($($opt_header_default)? {
+                            unsafe { self.$opt_header_name.rebased(&self.raw, slice) }
+                        }, {
+                            self.$opt_header_name.as_ref().map(|value| unsafe { value.rebased(&self.raw, slice) })
+                        }),
+                    )*)?
+                    $(
+                        $has_custom: (),
+                        custom: self.custom.iter().map(|header| unsafe { header.rebased(&self.raw, slice) }).collect(),
+                    )?
+                    $(
+                        $has_body: $crate::__private::functions::rebase(self.$has_body, &self.raw, slice),
+                    )?
+                    phantom: std::marker::PhantomData,
+                    raw,
+                }
+            }
+        }
+
+        #[doc = "Frames are equal if they have the same command, as it occurs in the frame, so that an alias differs,"]
+        #[doc = "the same headers, decoded and in the same order, and the same body, just as raw frames are compared."]
+        impl <'a> PartialEq for $name<'a> {
+            fn eq(&self, other: &Self) -> bool {
+                $crate::frame::eq_frames(self, other)
+            }
+        }
+
+        impl <'a> Eq for $name<'a> {}
+
+        impl <'a> std::hash::Hash for $name<'a> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                $crate::frame::hash_frame(self, state)
+            }
+        }
+
+        impl <'a> $crate::__private::Sealed for $name<'a> {}
+
+        impl <'a> $crate::frame::Frame for $name<'a> {
+            fn raw_bytes(&self) -> &[u8] {
+                &self.raw
+            }
+
+            fn into_raw(self) -> Vec<u8> {
                 self.raw
             }
         }
 
-        impl <'a> std::fmt::Debug for $name<'a> {
-             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-                write!(f, "{}{{", Self::NAME)?;
-                $(
-                    write!(f, " {}: '{}', ", stringify!($header_name), self.$header_name.value())?;
-                )*
+        #[doc = "This implementation serialises [`"$name"`] into a byte array."]
+        impl <'a> From<$name<'a>> for Vec<u8> {
+            fn from(frame: $name<'a>) -> Vec<u8> {
+                frame.raw
+            }
+        }
 
-                $($(
-                    write!(f, " {}: '{}', ", stringify!($opt_header_name),
-                    choose_from_presence!($( $opt_header_default )?
-                    {self.$opt_header_name.value().to_string() },
-                    { self.$opt_header_name.as_ref().map(|header|header.value().to_string()).unwrap_or("None".to_owned()) }))?;
-                )*)?
-                $(
-                    self.$has_body;
-                    write!(f, "body-length: {}", self.body().map(|body|body.len()).unwrap_or(0))?;
-                )?
+        #[doc = "Shows the command, the headers in order with their values decoded, and the length of the body."]
+        #[doc = "Sensitive headers, such as `passcode`, are redacted."]
+        impl <'a> std::fmt::Debug for $name<'a> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+                $crate::frame::debug_frame(self, f)
+            }
+        }
 
-                f.write_str("}}\n")
+        #[doc = "Formats the frame in the default `FrameFormat`, or the multi-line one with `{:#}`."]
+        impl <'a> std::fmt::Display for $name<'a> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+                $crate::frame::display_frame(self, f)
             }
         }
 
@@ -108,106 +219,156 @@ macro_rules! frame {
     }
 }
 
-macro_rules! frame_parser {
-    ( $name:ident, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt)?),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident])? ) => {
-        paste::paste! {
-            #[allow(unused)]
-            pub fn [<$name:lower _frame>]<E: 'static + FullError<&'static [u8], StompParseError>>(
-                mut frame: [<$name Frame>]<'static>
-            ) -> Result<[<$origin Frame>], StompParseError>{
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_frame_parser {
+    ( $name:ident, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt)?),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident $(: $length_header:ident)?])? ) => {
+        $crate::__private::paste::paste! {
+            pub fn [<$name:lower _frame>](
+                mut frame: [<$name Frame>]<'static>,
+                #[allow(unused_variables)] policy: $crate::ContentLengthPolicy,
+            ) -> Result<[<$name Frame>]<'static>, $crate::error::StompParseError> {
 
                 let bytes : *const [u8] = frame.raw.as_slice();
 
                 let input = unsafe { bytes.as_ref().unwrap() };
 
-                let (input,_) = command_line::<VerboseError<&[u8]>, StompParseError>(input).map_err(|_|StompParseError::new("Error parsing frame"))?;
+                let (input,_) = command_line::<VerboseError<&[u8]>, $crate::error::StompParseError>(input).map_err(|_|$crate::error::StompParseError::new("Error parsing frame"))?;
+
+                let mut lines = HeaderLines::new(input);
+
+                $(
+                    let mut $header_name: Option<$crate::headers::[<$header_type Value>]> = None;
+                )*
+                $($(
+                    let mut [<seen_ $opt_header_name>] = false;
+                )*)?
+
+                for line in &mut lines {
+                    let (name, value) = line?;
 
-                        let headers_parser = headers_parser::<'static, E>(
-                                vec![$(
-                            HeaderType::$header_type,
-                        )*],
-                        vec![$($(
-                            HeaderType::$opt_header_type,
-                        )*)?],
-                        true_if_present!(
+                    // Dispatch statically on the header name. As the specification requires, only the first
+                    // occurrence of a repeated header applies; the others are ignored.
+                    match $crate::headers::HeaderType::from_name(name) {
                         $(
-                            $has_custom
-                        )?)
-                            );
-
-                        let body_section = if true_if_present!($($has_body)?) {
-                            remaining_without_null::<'static>
-                        } else {
-                            null
-                        };
-
-                        let mut fnmut = context(
-                            stringify!([<$name _frame>]),
-                            map_res(tuple((headers_parser, body_section)), |x| {
-                                let headers = x.0;
-                                $(
-                                    let mut $header_name: Option<[<$header_type Value>]> = None;
-                                )*
-
-                                for header in headers {
-                                    match header {
-                                        $(
-                                        Header::$header_type(val) => { $header_name = Some(val); }
-                                        )*
-                                        $($(
-                                        Header::$opt_header_type(val) => { frame.$opt_header_name = choose_from_presence!( $($opt_header_default)? val, (Some(val))); }
-                                        )*)?
-                                        $(
-                                        Header::Custom(val)=> {
-                                            blank!($has_custom);
-                                            frame.custom.push(val);
-                                        }
-                                        )?
-                                        _ => {Err(StompParseError::new(format!("Unexpected header: {:?}",header)))?;}
-                                    }
-                                }
-
-
-                                $(
-                                    frame.$header_name = $header_name.ok_or_else(|| StompParseError::new(format!("Missing required header of type: {:?}",HeaderType::$header_type)))?;
-                                )*
-
-                                //         $has_custom,
-                                //     )?
-                                $(
-                                frame.$has_body =  x.1;
-                                )?
-
-                                Ok(())
+                        Some($crate::headers::HeaderType::$header_type) => {
+                            if $header_name.is_none() {
+                                $header_name = Some($crate::headers::[<$header_type Value>]::from_str(value)?);
                             }
-                        ));
-
-                        fnmut(input).map_err(|_|StompParseError::new("Error parsing frame"))?;
-                        drop(fnmut);
-                        Ok([<$origin Frame>]::$name(frame))
+                        }
+                        )*
+                        $($(
+                        Some($crate::headers::HeaderType::$opt_header_type) => {
+                            if ![<seen_ $opt_header_name>] {
+                                [<seen_ $opt_header_name>] = true;
+                                let val = $crate::headers::[<$opt_header_type Value>]::from_str(value)?;
+                                frame.$opt_header_name = $crate::__stomp_choose_from_presence!( $($opt_header_default)? val, (Some(val)));
+                            }
+                        }
+                        )*)?
+                        $(
+                        _ => {
+                            $crate::__stomp_blank!($has_custom);
+                            frame.custom.push($crate::headers::CustomValue::new(name, value));
+                        }
+                        )?
+                        #[allow(unreachable_patterns)]
+                        _ => {
+                            return Err($crate::error::StompParseError::new(format!("Unexpected header '{}' encountered", name)));
+                        }
                     }
+                }
 
+                $(
+                    frame.$header_name = $header_name.ok_or_else(|| $crate::error::StompParseError::new(format!("Missing required header of type: {:?}",$crate::headers::HeaderType::$header_type)))?;
+                )*
+
+                let body_section = if $crate::__stomp_true_if_present!($($has_body)?) {
+                    remaining_without_null::<VerboseError<&[u8]>>
+                } else {
+                    null
+                };
+
+                #[allow(unused_variables)]
+                let (_, body) = body_section(lines.remaining()).map_err(|_|$crate::error::StompParseError::new("Error parsing frame"))?;
+
+                $(
+                    frame.$has_body = body;
+                    $(
+                        if let Some(length) = frame.$length_header.as_ref() {
+                            policy.check(*length.value(), body.len())?;
+                        }
+                    )?
+                )?
 
+                Ok(frame)
+            }
         }
     };
 }
 
+/// Declares frame types, each with a builder and a parser, and an enum of them which is parsed from bytes by
+/// `TryFrom<Vec<u8>>`, just as the frames in [`client`](crate::client) and [`server`](crate::server) are declared.
+/// This allows the frames of extensions to STOMP to be modelled.
+///
+/// The first argument names the enum, `<Group>Frame`. Each frame follows, as
+///
+/// `(Name, "comment", COMMAND|ALIAS, Group, required: Type, ..., (optional: Type, ...), [custom: cus], [body: body: content_length])`
+///
+/// which declares the `NameFrame` and `NameFrameBuilder` types. The comment, the aliases, and each of the parts after
+/// the required headers are optional. Headers are given by the name of their accessor and their [`HeaderType`](crate::headers::HeaderType),
+/// for which the `<Type>Value` type is used. `[custom: cus]` allows other headers, which are kept as custom headers,
+/// and `[body: body]` allows a body, with `: content_length` naming the optional header which holds its length.
+///
+/// The generated code refers to what it uses by full paths, so it imports nothing into the module in which the macro
+/// is invoked.
+///
+/// ```
+/// mod broker {
+///     stomp_parser::frames! {
+///         Broker,
+///         (
+///             Notify,
+///             "Notifies the client of an event at a destination.",
+///             NOTIFY,
+///             Broker,
+///             destination: Destination,
+///             (content_type: ContentType, content_length: ContentLength),
+///             [custom: cus],
+///             [body: body: content_length]
+///         )
+///     }
+/// }
+///
+/// use broker::{BrokerFrame, NotifyFrame};
+/// use std::convert::TryFrom;
+///
+/// let frame = NotifyFrame::builder()
+///     .destination("news".to_owned())
+///     .add_custom_header("topic".to_owned(), "sport".to_owned())
+///     .body(b"extra".to_vec())
+///     .build();
+///
+/// match BrokerFrame::try_from(Vec::from(frame)).unwrap() {
+///     BrokerFrame::Notify(frame) => {
+///         assert_eq!("news", frame.destination().value());
+///         assert_eq!(Some(&5), frame.content_length().map(|length| length.value()));
+///         assert_eq!(Some(&b"extra"[..]), frame.body());
+///     }
+/// }
+///
+/// assert!(BrokerFrame::try_from(b"SEND\ndestination:news\n\n\x00".to_vec()).is_err());
+/// ```
+#[macro_export]
 macro_rules! frames {
     { $group_name:ident,
         $(
-            ( $name:ident, $($comment:literal,)? $command:ident$(|$alias:ident)*, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt$(: $opt_header_default_comment:literal)?)?),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident])? $(,$long_comment:literal)* )
+            ( $name:ident, $($comment:literal,)? $command:ident$(|$alias:ident)*, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt$(: $opt_header_default_comment:literal)?)?),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident $(: $length_header:ident)?])? $(,$long_comment:literal)* )
         ),+
     } => {
-        use crate::common::constants::*;
-        use crate::common::functions::*;
-
-        use crate::error::StompParseError;
-
-        use std::convert::TryFrom;
-
-        paste::paste! {
+        $crate::__private::paste::paste! {
             $(
-                frame! (
+                $crate::__stomp_frame!(
                     [<$name Frame>],
                     $($comment,)?
                     $command,
@@ -215,7 +376,7 @@ macro_rules! frames {
                     $(, $header_name : $header_type )*
                     $(,( $(  $opt_header_name : $opt_header_type $(: $opt_header_default $(: $opt_header_default_comment)?)? ),* ))?
                     $(,[custom: $has_custom])?
-                    $(,[body: $has_body])?
+                    $(,[body: $has_body $(: $length_header)?])?
                     $(,$long_comment)?
                 );
             )+
@@ -230,10 +391,58 @@ macro_rules! frames {
                 ),+
             }
 
-            #[doc = "This implementation serialises [`"$group_name Frame"`] into a byte array."]
-            impl Into<Vec<u8>> for [<$group_name Frame>] {
-                fn into(self) -> Vec<u8> {
+            impl Clone for [<$group_name Frame>] {
+                fn clone(&self) -> Self {
+                    match self {
+                        $(
+                            [<$group_name Frame>]::$name(frame) => [<$group_name Frame>]::$name(frame.clone()),
+                        )+
+                    }
+                }
+            }
+
+            #[doc = "Frames are equal if they are of the same kind, and equal as such."]
+            impl PartialEq for [<$group_name Frame>] {
+                fn eq(&self, other: &Self) -> bool {
+                    match (self, other) {
+                        $(
+                            ([<$group_name Frame>]::$name(frame), [<$group_name Frame>]::$name(other)) => frame == other,
+                        )+
+                        #[allow(unreachable_patterns)]
+                        _ => false,
+                    }
+                }
+            }
+
+            impl Eq for [<$group_name Frame>] {}
+
+            impl std::hash::Hash for [<$group_name Frame>] {
+                fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                    std::mem::discriminant(self).hash(state);
                     match self {
+                        $(
+                            [<$group_name Frame>]::$name(frame) => frame.hash(state),
+                        )+
+                    }
+                }
+            }
+
+            impl $crate::__private::Sealed for [<$group_name Frame>] {}
+
+            impl $crate::frame::Frame for [<$group_name Frame>] {
+                fn raw_bytes(&self) -> &[u8] {
+                    self.raw_bytes()
+                }
+
+                fn into_raw(self) -> Vec<u8> {
+                    self.into()
+                }
+            }
+
+            #[doc = "This implementation serialises [`"$group_name Frame"`] into a byte array."]
+            impl From<[<$group_name Frame>]> for Vec<u8> {
+                fn from(frame: [<$group_name Frame>]) -> Vec<u8> {
+                    match frame {
                         $(
                             [<$group_name Frame>]::$name(frame) => frame.into(),
                         )+
@@ -241,72 +450,103 @@ macro_rules! frames {
                 }
             }
 
-            impl std::fmt::Debug for [<$group_name Frame>] {
-                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+            impl [<$group_name Frame>] {
+                #[doc = "The commands of the frames the "$group_name:lower" can send, including aliases."]
+                pub const COMMANDS: &'static [&'static str] = &[$(stringify!($command) $(, stringify!($alias))*),+];
+
+                #[doc = "Parses a frame as `try_from` does, but validates `content-length` headers according to the policy."]
+                pub fn parse_with(bytes: Vec<u8>, policy: $crate::ContentLengthPolicy) -> Result<Self, $crate::error::StompParseError> {
+                    self::parsers::[<$group_name:lower _frame>](bytes, policy)
+                }
+
+                #[doc = "The bytes of this frame. For a parsed frame, these are exactly the bytes it was parsed from."]
+                pub fn raw_bytes(&self) -> &[u8] {
+                    match self {
+                        $(
+                            [<$group_name Frame>]::$name(frame) => frame.raw_bytes(),
+                        )+
+                    }
+                }
+
+                #[doc = "A copy of the frame with the header `name` set to `value`; see the `with_header` method of the"]
+                #[doc = "individual frames."]
+                pub fn with_header(&self, name: &str, value: &str) -> Result<Self, $crate::error::StompParseError> {
+                    match self {
+                        $(
+                            [<$group_name Frame>]::$name(frame) => frame.with_header(name, value).map([<$group_name Frame>]::$name),
+                        )+
+                    }
+                }
+
+                #[doc = "The number of bytes this frame serialises to."]
+                pub fn encoded_len(&self) -> usize {
                     match self {
                         $(
-                            [<$group_name Frame>]::$name(frame) => frame.fmt(f),
+                            [<$group_name Frame>]::$name(frame) => frame.encoded_len(),
+                        )+
+                    }
+                }
+
+                #[doc = "Appends the serialised frame to the buffer, without consuming the frame."]
+                pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+                    match self {
+                        $(
+                            [<$group_name Frame>]::$name(frame) => frame.encode_into(bytes),
                         )+
                     }
                 }
             }
 
+            impl std::fmt::Debug for [<$group_name Frame>] {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+                    $crate::frame::debug_frame(self, f)
+                }
+            }
+
+            impl std::fmt::Display for [<$group_name Frame>] {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+                    $crate::frame::display_frame(self, f)
+                }
+            }
+
             #[doc = "Parses a `" $group_name "Frame`  from the data contained in the provided vector of bytes."]
-            impl TryFrom<Vec<u8>> for [<$group_name Frame>]{
-                        type Error = StompParseError;
-                        fn try_from(bytes: Vec<u8>) -> Result<Self, StompParseError> {
-                            self::parsers::[<$group_name:lower _frame>](bytes)
+            impl std::convert::TryFrom<Vec<u8>> for [<$group_name Frame>]{
+                        type Error = $crate::error::StompParseError;
+                        fn try_from(bytes: Vec<u8>) -> Result<Self, $crate::error::StompParseError> {
+                            self::parsers::[<$group_name:lower _frame>](bytes, $crate::ContentLengthPolicy::default())
                          }
             }
 
             mod parsers {
                 use super::*;
-                use crate::parser::headers::headers_parser;
-                use crate::parser::{null,remaining_without_null, command_line};
-                use crate::error::FullError;
-                use crate::error::StompParseError;
-                use nom::combinator::map_res;
-                use nom::error::context;
-                use nom::error::VerboseError;
-                use nom::sequence::tuple;
+                use $crate::__private::HeaderLines;
+                use $crate::__private::{null, remaining_without_null, command_line};
+                use $crate::__private::VerboseError;
                  $(
-                    frame_parser! (
+                    $crate::__stomp_frame_parser!(
                         $name,
                         $group_name
                         $(, $header_name : $header_type )*
                         $(,( $(  $opt_header_name : $opt_header_type $(: $opt_header_default )? ),* ))?
                         $(,[custom: $has_custom])?
-                        $(,[body: $has_body])?
+                        $(,[body: $has_body $(: $length_header)?])?
                     );
                 )+
 
-                pub fn [<$group_name:lower _frame>](input: Vec<u8>) -> Result<[<$group_name Frame>], StompParseError>
+                pub fn [<$group_name:lower _frame>](input: Vec<u8>, policy: $crate::ContentLengthPolicy) -> Result<[<$group_name Frame>], $crate::error::StompParseError>
                 {
-                    let slice = input.as_slice();
-
-                    let (_,command_string) = command_line::<VerboseError<&[u8]>, StompParseError>(slice).map_err(|_|StompParseError::new("Error parsing frame"))?;
-
-                    let initialiser: Box<dyn FnOnce(Vec<u8>)-> [<$group_name Frame>]> = std::str::from_utf8(command_string)
-                        .map_err(|_|StompParseError::new("badly formed command string, not utf8"))
-                        .and_then(move |command_string| match command_string {
-                            $(
+                    let (_,command_string) = command_line::<VerboseError<&[u8]>, $crate::error::StompParseError>(input.as_slice()).map_err(|_|$crate::error::StompParseError::new("Error parsing frame"))?;
 
-                                stringify!($command) => Ok(Box::new(|input|[<$group_name Frame>]::$name([<$name Frame>]::init(input))) as Box<dyn FnOnce(Vec<u8>)-> [<$group_name Frame>]>),
-                                $(
-                                    stringify!($alias) => Ok(Box::new(|input|[<$group_name Frame>]::$name([<$name Frame>]::init(input)))),
-                                )*
-                            )+
-                            _ => Err(StompParseError::new(format!("Unknown command {}", command_string)))
-                        })?;
+                    let command_string = std::str::from_utf8(command_string)
+                        .map_err(|_|$crate::error::StompParseError::new("badly formed command string, not utf8"))?;
 
-                    let frame = initialiser(input);
-
-                    match frame {
+                    match command_string {
                         $(
-                        [<$group_name Frame>]::$name(inner) =>  {
-                            [<$name:lower _frame>]::<VerboseError<&[u8]>>(inner)
-                        }
+                            stringify!($command) $(| stringify!($alias))* => {
+                                [<$name:lower _frame>]([<$name Frame>]::init(input), policy).map([<$group_name Frame>]::$name)
+                            }
                         )+
+                        _ => Err($crate::error::StompParseError::new(format!("Unknown command {}", command_string)))
                     }
                 }
 
@@ -5,6 +5,15 @@ mod macros;
 
 mod utils;
 
+pub mod any;
+mod format;
+pub mod frame;
+pub mod raw;
+pub mod typestate;
+
+#[cfg(test)]
+mod roundtrip;
+
 #[allow(non_snake_case)]
 #[allow(unused_parens)]
 #[allow(clippy::new_without_default)]
@@ -12,7 +21,14 @@ pub mod client {
     //! Implements the model for the frames that a STOMP client can send, as specified in
     //! the [STOMP Protocol Specification,Version 1.2](https://stomp.github.io/stomp-specification-1.2.html).
 
+    use crate::error::{BodyCodecError, BodyDecodeError};
+    #[cfg(feature = "json")]
+    use crate::model::body::JsonCodec;
+    use crate::model::body::{text_body, BodyCodec, MediaType};
     use crate::model::headers::*;
+    use crate::server::ErrorFrame;
+    use std::borrow::Cow;
+    use std::convert::TryFrom;
 
     frames! {
         Client,
@@ -92,7 +108,7 @@ pub mod client {
                 receipt: Receipt
             ),
             [custom: cus],
-            [body: body]
+            [body: body: content_length]
         ),
         (
             Subscribe,
@@ -117,7 +133,65 @@ pub mod client {
         )
     }
 
-    impl<'a> SendFrame<'a> {}
+    impl<'a> SendFrame<'a> {
+        /// The body decoded as text, according to the `charset` of the `content-type` header, or as UTF-8 if it
+        /// specifies none.
+        pub fn text_body(&self) -> Result<Cow<'a, str>, BodyDecodeError> {
+            text_body(self.content_type.as_ref(), self.body)
+        }
+    }
+
+    impl<'a> ConnectFrame<'a> {
+        /// Negotiates the version of the connection, which the server sends in the `version` header of its
+        /// `CONNECTED` frame; see [`StompVersions::negotiate`]. If the server supports none of the versions the
+        /// client accepts, it should reply with the returned `ERROR` frame instead.
+        // The error is the frame to be sent, and only returned once per connection, so it is not boxed
+        #[allow(clippy::result_large_err)]
+        pub fn negotiate_version(
+            &self,
+            supported: &StompVersions,
+        ) -> Result<StompVersion, ErrorFrame<'static>> {
+            self.accept_version()
+                .value()
+                .negotiate(supported)
+                .ok_or_else(|| ErrorFrame::unsupported_versions(supported))
+        }
+    }
+
+    impl SendFrameBuilder {
+        /// Sets the body to the value encoded by the codec, and the `content-type` and `content-length` headers to
+        /// match it.
+        pub fn encoded_body<T, C: BodyCodec<T> + ?Sized>(
+            self,
+            codec: &C,
+            value: &T,
+        ) -> Result<Self, BodyCodecError> {
+            self.typed_body(codec.media_type(), codec.encode(value)?)
+        }
+
+        /// Sets the body to the value encoded as JSON, and the `content-type` and `content-length` headers to match
+        /// it.
+        #[cfg(feature = "json")]
+        pub fn json_body<T: serde::Serialize + ?Sized>(
+            self,
+            value: &T,
+        ) -> Result<Self, BodyCodecError> {
+            self.typed_body(
+                MediaType::new("application", "json"),
+                JsonCodec::encode_value(value)?,
+            )
+        }
+
+        fn typed_body(self, media_type: MediaType, body: Vec<u8>) -> Result<Self, BodyCodecError> {
+            let length = u32::try_from(body.len())
+                .map_err(|_| BodyCodecError::Encode("The body is too long".to_owned()))?;
+
+            Ok(self
+                .content_type(media_type.to_string())
+                .content_length(length)
+                .body(body))
+        }
+    }
 }
 
 #[allow(non_snake_case)]
@@ -126,7 +200,18 @@ pub mod client {
 pub mod server {
     //! Implements the model for the frames that a STOMP server can send, as specified in the
     //! [STOMP Protocol Specification,Version 1.2](https://stomp.github.io/stomp-specification-1.2.html).
+    use crate::client::{SendFrame, SubscribeFrame};
+    use crate::common::constants::TERMINATOR;
+    use crate::error::{BodyCodecError, BodyDecodeError};
+    use crate::frame::Frame;
+    #[cfg(any(feature = "json", feature = "msgpack"))]
+    use crate::model::body::decode_serde_body;
+    use crate::model::body::{decode_body, text_body, BodyCodec};
     use crate::model::headers::*;
+    use crate::model::typestate::{ClientAck, Set, Unset};
+    use crate::ContentLengthPolicy;
+    use std::borrow::Cow;
+    use std::convert::TryFrom;
     frames! {
         Server,
         (
@@ -149,9 +234,13 @@ pub mod server {
             Error,
             ERROR,
             Server,
-            (message: Message),
+            (
+                message: Message,
+                content_type: ContentType,
+                content_length: ContentLength
+            ),
             [custom: cus],
-            [body: body]),
+            [body: body: content_length]),
         (
             Message,
             MESSAGE,
@@ -164,15 +253,212 @@ pub mod server {
                 content_length: ContentLength
             ),
             [custom: cus],
-            [body: body]
+            [body: body: content_length]
         )
     }
 
     impl<'a> ErrorFrame<'a> {
         pub fn from_message(message: &str) -> Self {
-            ErrorFrameBuilder::new().message(message.to_owned()).build()
+            ErrorFrame::builder().message(message.to_owned()).build()
+        }
+
+        /// The `ERROR` frame with which a server rejects a `CONNECT` frame, because it supports none of the versions
+        /// the client accepts. As the specification requires, its `version` header, and its body, list the
+        /// `supported` versions.
+        pub fn unsupported_versions(supported: &StompVersions) -> Self {
+            let listed = supported
+                .iter()
+                .map(StompVersion::to_string)
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            ErrorFrame::builder()
+                .message("Unsupported protocol version".to_owned())
+                .add_custom_header(VersionValue::NAME.to_owned(), supported.to_string())
+                .content_type("text/plain".to_owned())
+                .body(format!("Supported protocol versions are {}", listed).into_bytes())
+                .build()
+        }
+
+        /// The body decoded as text, according to the `charset` of the `content-type` header, or as UTF-8 if it
+        /// specifies none.
+        pub fn text_body(&self) -> Result<Cow<'a, str>, BodyDecodeError> {
+            text_body(self.content_type.as_ref(), self.body)
         }
     }
+
+    impl<'a> MessageFrame<'a> {
+        /// The body decoded as text, according to the `charset` of the `content-type` header, or as UTF-8 if it
+        /// specifies none.
+        pub fn text_body(&self) -> Result<Cow<'a, str>, BodyDecodeError> {
+            text_body(self.content_type.as_ref(), self.body)
+        }
+
+        /// The body decoded by the codec, which must accept the `content-type` of the frame.
+        pub fn decode_body_with<T, C: BodyCodec<T> + ?Sized>(
+            &self,
+            codec: &C,
+        ) -> Result<T, BodyCodecError> {
+            decode_body(codec, self.content_type.as_ref(), self.body)
+        }
+
+        /// The body decoded by whichever of the enabled serde codecs (JSON or MessagePack) accepts the
+        /// `content-type` of the frame.
+        #[cfg(any(feature = "json", feature = "msgpack"))]
+        pub fn decode_body<T: serde::de::DeserializeOwned>(&self) -> Result<T, BodyCodecError> {
+            decode_serde_body(self.content_type.as_ref(), self.body)
+        }
+    }
+
+    impl MessageFrame<'static> {
+        /// Creates the `MESSAGE` frame which delivers the message sent in `send` to the subscription with id
+        /// `subscription`. See [`MessageFrameBuilder::from_send`] for which headers are carried over.
+        pub fn from_send(send: &SendFrame, message_id: String, subscription: String) -> Self {
+            MessageFrameBuilder::from_send(send, message_id, subscription).build()
+        }
+
+        /// As [`MessageFrame::from_send`], but takes ownership of the `SEND` frame so that its buffer, and the body
+        /// in it, is reused rather than copied. Only the headers are rewritten. An `ack` header can be added with
+        /// `with_header`.
+        pub fn from_owned_send(
+            send: SendFrame<'static>,
+            message_id: String,
+            subscription: String,
+        ) -> Self {
+            let mut builder = message_headers(&send, message_id).subscription(subscription);
+            // The builder has no body to take the length from, so it is set explicitly, as `from_send` sets it
+            if let Ok(length) = u32::try_from(Frame::body(&send).len()) {
+                builder = builder.auto_content_length(false).content_length(length);
+            }
+
+            let mut headers = Vec::new();
+            builder.encode_into(&mut headers);
+            // Drop the terminator, the body follows the headers
+            headers.truncate(headers.len() - TERMINATOR.len());
+
+            // Unlike the body of a built frame without one, this is always in the buffer, where it follows the headers
+            let body_start =
+                Frame::body(&send).as_ptr() as usize - send.raw_bytes().as_ptr() as usize;
+
+            let mut raw = Vec::from(send);
+            raw.splice(..body_start, headers);
+
+            parsers::message_frame(MessageFrame::init(raw), ContentLengthPolicy::default())
+                .expect("Should never fail, because built from a valid frame")
+        }
+    }
+
+    impl MessageFrameBuilder {
+        /// A builder for the `MESSAGE` frame which delivers the message sent in `send` to the subscription with id
+        /// `subscription`. The `destination` and `content-type` headers, the custom headers and the body are carried
+        /// over, and `content-length` is set to the length of the body, whatever the `SEND` declared, so that the
+        /// `MESSAGE` is valid even if the `SEND` was only accepted under a lenient [`ContentLengthPolicy`].
+        /// `transaction` and `receipt` only concern the `SEND` itself and are dropped, as are custom headers which
+        /// use the name of a header defined by the specification, so that a client cannot forge, for example, the
+        /// `message-id` or `ack` headers.
+        pub fn from_send(send: &SendFrame, message_id: String, subscription: String) -> Self {
+            message_headers(send, message_id)
+                .subscription(subscription)
+                .body(send.body().unwrap_or_default().to_vec())
+        }
+
+        /// As [`MessageFrameBuilder::from_send`], for a subscription which requires acknowledgement, so that the
+        /// `ack` header can be set as well.
+        pub fn from_send_to_client_ack(
+            send: &SendFrame,
+            message_id: String,
+            subscription: &ClientAckSubscription,
+        ) -> MessageFrameBuilder<Set, Set, ClientAck> {
+            message_headers(send, message_id)
+                .client_ack_subscription(subscription)
+                .body(send.body().unwrap_or_default().to_vec())
+        }
+    }
+
+    impl<MessageIdState, DestinationState>
+        MessageFrameBuilder<MessageIdState, DestinationState, Unset>
+    {
+        /// Sets the `subscription` header to the id of the subscription, which requires acknowledgement, so that
+        /// the `ack` header can be set as well. Only available while the `subscription` header is unset.
+        pub fn client_ack_subscription(
+            mut self,
+            subscription: &ClientAckSubscription,
+        ) -> MessageFrameBuilder<MessageIdState, DestinationState, ClientAck> {
+            self.subscription = Some(subscription.id.clone());
+            self.retype()
+        }
+    }
+
+    impl<MessageIdState, DestinationState>
+        MessageFrameBuilder<MessageIdState, DestinationState, ClientAck>
+    {
+        /// The value of the `ack` header, which the client uses as the `id` of the `ACK` or `NACK` frame for this
+        /// message, replacing any set before. Only available once the subscription is known to require
+        /// acknowledgement.
+        pub fn ack(mut self, ack: String) -> Self {
+            self.custom.retain(|(name, _)| name != AckValue::NAME);
+            self.add_custom_header(AckValue::NAME.to_owned(), ack)
+        }
+    }
+
+    /// A subscription which requires the messages sent to it to be acknowledged, because its `ack` header is
+    /// `client` or `client-individual`. Messages to it may carry an `ack` header; see
+    /// [`MessageFrameBuilder::client_ack_subscription`] and [`MessageFrameBuilder::from_send_to_client_ack`].
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct ClientAckSubscription {
+        id: String,
+        ack_type: AckType,
+    }
+
+    impl ClientAckSubscription {
+        /// The subscription made by the `SUBSCRIBE` frame, if it requires acknowledgement.
+        pub fn from_subscribe<'a>(frame: &'a SubscribeFrame<'a>) -> Option<Self> {
+            match frame.ack_type().value() {
+                AckType::Auto => None,
+                ack_type => Some(ClientAckSubscription {
+                    id: frame.id().to_owned_value(),
+                    ack_type: ack_type.clone(),
+                }),
+            }
+        }
+
+        /// The id of the subscription.
+        pub fn id(&self) -> &str {
+            &self.id
+        }
+
+        /// How the messages sent to the subscription are acknowledged.
+        pub fn ack_type(&self) -> &AckType {
+            &self.ack_type
+        }
+    }
+
+    /// A builder with the headers of the `MESSAGE` which delivers the `SEND`, except for `subscription`.
+    fn message_headers(
+        send: &SendFrame,
+        message_id: String,
+    ) -> MessageFrameBuilder<Set, Set, Unset> {
+        let mut builder = MessageFrame::builder()
+            .message_id(message_id)
+            .destination(send.destination().to_owned_value());
+
+        if let Some(content_type) = send.content_type() {
+            builder = builder.content_type(content_type.to_owned_value());
+        }
+        for header in &send.custom {
+            let name = header
+                .decoded_name()
+                .expect("Header names are validated when parsed or built");
+            if HeaderType::from_name(&name).is_none() {
+                let value = header
+                    .decoded_value()
+                    .expect("Header values are validated when parsed or built");
+                builder = builder.add_custom_header(name.to_string(), value.to_string());
+            }
+        }
+
+        builder
+    }
 }
 
 #[cfg(test)]
@@ -183,13 +469,14 @@ mod test {
 
     use crate::model::headers::*;
     use std::convert::TryFrom;
-    use std::convert::TryInto;
     use std::str::FromStr;
     use std::thread;
 
     #[test]
     fn new_builder_can_be_build() {
-        let frame = SendFrameBuilder::new("foo/bar".to_owned()).build();
+        let frame = SendFrame::builder()
+            .destination("foo/bar".to_owned())
+            .build();
 
         assert_eq!("foo/bar", frame.destination().value());
     }
@@ -209,6 +496,73 @@ mod test {
         }
     }
 
+    #[test]
+    fn rebuilds_connect_with_unknown_versions_as_parsed() {
+        let raw = b"CONNECT\nhost:foo\naccept-version:1.1,1.2,funk\nheart-beat:010,20\n\n\x00";
+        let parsed = match ClientFrame::try_from(raw.to_vec()) {
+            Ok(ClientFrame::Connect(frame)) => frame,
+            _ => panic!("Expected a connect frame"),
+        };
+
+        assert_eq!(&HeartBeatIntervals::new(10, 20), parsed.heartbeat().value());
+        assert_eq!("heart-beat:010,20", parsed.heartbeat().to_string());
+
+        let rebuilt = ConnectFrameBuilder::from(&parsed).build();
+
+        assert_eq!(&raw[..], rebuilt.raw_bytes());
+        assert_eq!(
+            &raw[..],
+            ConnectFrameBuilder::from(&rebuilt).build().raw_bytes()
+        );
+
+        // A changed value is written as it is displayed
+        let rebuilt = ConnectFrameBuilder::from(&parsed)
+            .heartbeat(HeartBeatIntervals::new(10, 30))
+            .build();
+
+        assert_eq!(
+            &b"CONNECT\nhost:foo\naccept-version:1.1,1.2,funk\nheart-beat:10,30\n\n\x00"[..],
+            rebuilt.raw_bytes()
+        );
+    }
+
+    #[test]
+    fn rebuilds_send_with_content_length_as_parsed() {
+        let raw = b"SEND\ndestination:a\ncontent-length:04\n\nbody\x00";
+        let parsed = match ClientFrame::try_from(raw.to_vec()) {
+            Ok(ClientFrame::Send(frame)) => frame,
+            _ => panic!("Expected a send frame"),
+        };
+
+        let builder = SendFrameBuilder::from(&parsed);
+
+        assert_eq!(raw.len(), builder.encoded_len());
+        assert_eq!(&raw[..], builder.build().raw_bytes());
+    }
+
+    #[test]
+    fn negotiate_version_lists_supported_versions_on_failure() {
+        let frame = ConnectFrame::builder()
+            .host("foo".to_owned())
+            .accept_version(StompVersions::from_str("1.0,1.1").unwrap())
+            .build();
+
+        assert_eq!(
+            Ok(StompVersion::V1_1),
+            frame.negotiate_version(&StompVersions::from_str("1.1,1.2").unwrap())
+        );
+
+        let error = frame
+            .negotiate_version(&StompVersions::from_str("1.2,2.1").unwrap())
+            .unwrap_err();
+
+        assert_eq!(
+            &b"ERROR\nmessage:Unsupported protocol version\ncontent-type:text/plain\ncontent-length:39\n\
+            version:1.2,2.1\n\nSupported protocol versions are 1.2 2.1\x00"[..],
+            error.raw_bytes()
+        );
+    }
+
     #[test]
     fn parses_connect_with_custom_headers() {
         let result = ClientFrame::try_from(
@@ -228,12 +582,11 @@ mod test {
 
     #[test]
     fn writes_connect_with_custom_headers() {
-        let frame = ConnectFrameBuilder::new(
-            "foo".to_owned(),
-            StompVersions::from_str("1.1,1.2").unwrap(),
-        )
-        .add_custom_header("foo".to_owned(), "bar".to_owned())
-        .build();
+        let frame = ConnectFrame::builder()
+            .host("foo".to_owned())
+            .accept_version(StompVersions::from_str("1.1,1.2").unwrap())
+            .add_custom_header("foo".to_owned(), "bar".to_owned())
+            .build();
 
         let displayed = String::from_utf8(frame.into()).unwrap();
 
@@ -243,9 +596,561 @@ mod test {
         );
     }
 
+    #[test]
+    fn builder_escapes_header_values() {
+        let frame = SendFrame::builder()
+            .destination("stairway:to\nheaven\\".to_owned())
+            .add_custom_header("fo:o".to_owned(), "b\rar".to_owned())
+            .build();
+
+        let bytes: Vec<u8> = frame.into();
+
+        assert_eq!(
+            b"SEND\ndestination:stairway\\cto\\nheaven\\\\\nfo\\co:b\\rar\n\n\x00".to_vec(),
+            bytes
+        );
+
+        let Ok(ClientFrame::Send(frame)) = ClientFrame::try_from(bytes) else {
+            panic!("Send Frame not parsed correctly")
+        };
+
+        assert_eq!(
+            "stairway:to\nheaven\\",
+            &*frame.destination().decoded_value().unwrap()
+        );
+        assert_eq!("fo:o", &*frame.custom[0].decoded_name().unwrap());
+        assert_eq!("b\rar", &*frame.custom[0].decoded_value().unwrap());
+    }
+
+    #[test]
+    fn builds_and_parses_empty_header_values() {
+        let frame = SendFrame::builder()
+            .destination("".to_owned())
+            .add_custom_header("foo".to_owned(), "".to_owned())
+            .build();
+
+        let Ok(ClientFrame::Send(frame)) = ClientFrame::try_from(Vec::<u8>::from(frame)) else {
+            panic!("Send Frame not parsed correctly")
+        };
+
+        assert_eq!("", frame.destination().value());
+        assert_eq!("", *frame.custom[0].value());
+    }
+
+    #[test]
+    fn parsed_frame_keeps_raw_bytes() {
+        let bytes = b"SEND\r\ndestination:foo\r\nx-custom:a\\cb\n\r\nbody\x00".to_vec();
+
+        let frame = ClientFrame::try_from(bytes.clone()).unwrap();
+
+        assert_eq!(bytes.as_slice(), frame.raw_bytes());
+    }
+
+    #[test]
+    fn with_header_replaces_header_in_place() {
+        let frame = ClientFrame::try_from(
+            b"SEND\r\ndestination:foo\r\nx-custom:a\\cb\n\r\nbody\x00".to_vec(),
+        )
+        .unwrap();
+
+        let frame = frame.with_header("destination", "bar:baz").unwrap();
+
+        assert_eq!(
+            b"SEND\r\ndestination:bar\\cbaz\r\nx-custom:a\\cb\n\r\nbody\x00",
+            frame.raw_bytes()
+        );
+        if let ClientFrame::Send(frame) = frame {
+            assert_eq!("bar:baz", &*frame.destination().decoded_value().unwrap());
+            assert_eq!(b"body", frame.body().unwrap());
+        } else {
+            panic!("Send Frame not parsed correctly");
+        }
+    }
+
+    #[test]
+    fn with_header_replaces_first_of_repeated_headers() {
+        let frame =
+            ClientFrame::try_from(b"SEND\ndestination:first\ndestination:second\n\n\x00".to_vec())
+                .unwrap()
+                .with_header("destination", "third")
+                .unwrap();
+
+        assert_eq!(
+            b"SEND\ndestination:third\ndestination:second\n\n\x00",
+            frame.raw_bytes()
+        );
+        if let ClientFrame::Send(frame) = frame {
+            assert_eq!("third", frame.destination().value());
+        } else {
+            panic!("Send Frame not parsed correctly");
+        }
+    }
+
+    #[test]
+    fn with_header_inserts_with_terminator_of_command_line() {
+        let frame = ClientFrame::try_from(b"BEGIN\r\ntransaction:t-1\r\n\r\n\x00".to_vec())
+            .unwrap()
+            .with_header("receipt", "r-1")
+            .unwrap();
+
+        assert_eq!(
+            b"BEGIN\r\nreceipt:r-1\r\ntransaction:t-1\r\n\r\n\x00",
+            frame.raw_bytes()
+        );
+    }
+
+    #[test]
+    fn with_header_adds_missing_header() {
+        let frame = ClientFrame::try_from(b"SUBSCRIBE\ndestination:foo\nid:1\n\n\x00".to_vec())
+            .unwrap()
+            .with_header("receipt", "r-1")
+            .unwrap();
+
+        assert_eq!(
+            b"SUBSCRIBE\nreceipt:r-1\ndestination:foo\nid:1\n\n\x00",
+            frame.raw_bytes()
+        );
+        if let ClientFrame::Subscribe(frame) = frame {
+            assert_eq!("r-1", frame.receipt().unwrap().value());
+        } else {
+            panic!("Subscribe Frame not parsed correctly");
+        }
+    }
+
+    #[test]
+    fn with_header_rejects_disallowed_header() {
+        let frame = ClientFrame::try_from(b"BEGIN\ntransaction:t-1\n\n\x00".to_vec()).unwrap();
+
+        assert!(frame.with_header("x-custom", "foo").is_err());
+
+        // The frame can still be forwarded as it was
+        assert_eq!(b"BEGIN\ntransaction:t-1\n\n\x00", frame.raw_bytes());
+    }
+
+    #[test]
+    fn with_header_keeps_frame_on_invalid_value() {
+        let frame = send_frame(b"SEND\ndestination:a\n\nbody\x00");
+
+        assert!(frame.with_header("content-length", "four").is_err());
+        assert_eq!(b"SEND\ndestination:a\n\nbody\x00", frame.raw_bytes());
+        assert_eq!("a", frame.destination().value());
+    }
+
+    #[test]
+    fn parsed_frame_converts_to_builder() {
+        let frame = ServerFrame::try_from(
+            b"MESSAGE\nmessage-id:m-1\ndestination:a\\cb\nsubscription:s-1\nx-custom:c\n\nbody\x00"
+                .to_vec(),
+        )
+        .unwrap();
+
+        let ServerFrame::Message(frame) = frame else {
+            panic!("Message Frame not parsed correctly");
+        };
+
+        let copy = MessageFrameBuilder::from(&frame)
+            .content_type("text/plain".to_owned())
+            .build();
+
+        assert_eq!(
+            b"MESSAGE\nmessage-id:m-1\ndestination:a\\cb\nsubscription:s-1\ncontent-type:text/plain\nx-custom:c\n\nbody\x00",
+            copy.raw_bytes()
+        );
+        assert_eq!(None, frame.content_type());
+    }
+
+    fn send_frame(bytes: &[u8]) -> SendFrame<'static> {
+        match ClientFrame::try_from(bytes.to_vec()) {
+            Ok(ClientFrame::Send(frame)) => frame,
+            _ => panic!("Send Frame not parsed correctly"),
+        }
+    }
+
+    #[test]
+    fn clone_points_into_its_own_buffer() {
+        let frame = send_frame(SEND);
+        let clone = frame.clone();
+        let range = clone.raw_bytes().as_ptr_range();
+
+        assert!(range.contains(&clone.destination().value().as_ptr()));
+        assert!(range.contains(&clone.custom[0].value().as_ptr()));
+        assert!(range.contains(&clone.body().unwrap().as_ptr()));
+
+        drop(frame);
+
+        assert_eq!("a\\cb", clone.destination().value());
+        assert_eq!(Some(&4), clone.content_length().map(|value| value.value()));
+        assert_eq!(Some(&b"body"[..]), clone.body());
+    }
+
+    #[test]
+    fn frames_are_equal_if_headers_decode_alike() {
+        let parsed =
+            ClientFrame::try_from(b"SEND\r\ndestination:a\\cb\r\nfoo:bar\n\nbody\x00".to_vec())
+                .unwrap();
+        let built = ClientFrame::Send(
+            SendFrame::builder()
+                .destination("a:b".to_owned())
+                .auto_content_length(false)
+                .add_custom_header("foo".to_owned(), "bar".to_owned())
+                .body(b"body".to_vec())
+                .build(),
+        );
+
+        assert_eq!(parsed, built);
+        assert_eq!(parsed, parsed.clone());
+        assert_ne!(parsed, built.clone().with_header("foo", "baz").unwrap());
+        assert_ne!(
+            ClientFrame::try_from(b"COMMIT\ntransaction:t\n\n\x00".to_vec()).unwrap(),
+            ClientFrame::try_from(b"ABORT\ntransaction:t\n\n\x00".to_vec()).unwrap()
+        );
+
+        let frames: std::collections::HashSet<ClientFrame> =
+            vec![parsed, built].into_iter().collect();
+        assert_eq!(1, frames.len());
+    }
+
+    #[test]
+    fn aliased_commands_differ_for_typed_and_raw_frames_alike() {
+        let connect = b"CONNECT\naccept-version:1.2\nhost:a\n\n\x00".to_vec();
+        let stomp = b"STOMP\naccept-version:1.2\nhost:a\n\n\x00".to_vec();
+
+        let typed = |bytes: &Vec<u8>| ClientFrame::try_from(bytes.clone()).unwrap();
+        let raw = |bytes: &Vec<u8>| crate::raw::RawFrame::try_from(bytes.clone()).unwrap();
+
+        assert_ne!(typed(&connect), typed(&stomp));
+        assert_ne!(raw(&connect), raw(&stomp));
+        assert_eq!(typed(&stomp), typed(&stomp));
+        assert_eq!(raw(&stomp), raw(&stomp));
+    }
+
+    crate::header!(Attempts, "attempts", u16, 1);
+
+    #[test]
+    fn custom_headers_are_typed() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .custom_header::<AttemptsValue>(1)
+            .custom_header::<AttemptsValue>(3)
+            .build();
+
+        assert_eq!(
+            b"SEND\ndestination:a\nattempts:3\n\n\x00",
+            frame.raw_bytes()
+        );
+        assert_eq!(
+            3,
+            *frame
+                .custom_header::<AttemptsValue>()
+                .unwrap()
+                .unwrap()
+                .value()
+        );
+
+        let frame = send_frame(b"SEND\ndestination:a\n\n\x00");
+        let attempts = frame.custom_header::<AttemptsValue>().unwrap();
+        assert_eq!(1, *attempts.unwrap_or_default().value());
+
+        let frame = send_frame(b"SEND\ndestination:a\nattempts:many\n\n\x00");
+        assert!(frame.custom_header::<AttemptsValue>().is_err());
+    }
+
+    // Declares items with the names of those the generated code uses, which would clash with any it imported
+    #[allow(dead_code)]
+    mod extension {
+        pub struct Set;
+        pub struct StompParseError;
+        fn decode_str() {}
+
+        crate::header!(Tag, "tag", u8, 0);
+
+        crate::frames! {
+            Extension,
+            (
+                Ping,
+                PING,
+                Extension,
+                (receipt: Receipt, content_length: ContentLength),
+                [custom: cus],
+                [body: body: content_length]
+            )
+        }
+    }
+
+    #[test]
+    fn frames_adds_no_imports_to_its_module() {
+        let frame = extension::PingFrame::builder()
+            .receipt("r-1".to_owned())
+            .custom_header::<extension::TagValue>(9)
+            .body(b"body".to_vec())
+            .build();
+
+        match extension::ExtensionFrame::try_from(Vec::from(frame)) {
+            Ok(extension::ExtensionFrame::Ping(frame)) => {
+                assert_eq!("r-1", frame.receipt().unwrap().value());
+                assert_eq!(
+                    9,
+                    *frame
+                        .custom_header::<extension::TagValue>()
+                        .unwrap()
+                        .unwrap()
+                        .value()
+                );
+                assert_eq!(Some(&b"body"[..]), frame.body());
+            }
+            _ => panic!("Ping Frame not parsed correctly"),
+        }
+    }
+
+    // Only some of the methods of the header value are used by the test
+    #[allow(dead_code)]
+    mod broken {
+        /// A value which, contrary to the contract of `Display`, fails to display.
+        #[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+        pub struct Unprintable;
+
+        impl std::str::FromStr for Unprintable {
+            type Err = ();
+
+            fn from_str(_: &str) -> Result<Self, ()> {
+                Ok(Unprintable)
+            }
+        }
+
+        impl std::fmt::Display for Unprintable {
+            fn fmt(&self, _: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                Err(std::fmt::Error)
+            }
+        }
+
+        crate::header!(Broken, "broken", Unprintable, Unprintable);
+    }
+
+    #[test]
+    #[should_panic]
+    fn custom_header_panics_when_value_fails_to_display() {
+        let frame = send_frame(b"SEND\ndestination:a\nbroken:x\n\n\x00");
+        let value = frame
+            .custom_header::<broken::BrokenValue>()
+            .unwrap()
+            .unwrap();
+
+        SendFrame::builder()
+            .destination("a".to_owned())
+            .custom_header::<broken::BrokenValue>(value.to_owned_value());
+    }
+
+    const SEND: &[u8] =
+        b"SEND\ndestination:a\\cb\ntransaction:t-1\nreceipt:r-1\ncontent-type:text/plain\n\
+        content-length:4\nx-custom:c\nmessage-id:forged\n\nbody\x00";
+
+    const MESSAGE: &[u8] = b"MESSAGE\nmessage-id:m-1\ndestination:a\\cb\nsubscription:s-1\n\
+        content-type:text/plain\ncontent-length:4\nx-custom:c\n\nbody\x00";
+
+    #[test]
+    fn message_from_send_propagates_headers_and_body() {
+        let send = send_frame(SEND);
+
+        let message = MessageFrame::from_send(&send, "m-1".to_owned(), "s-1".to_owned());
+
+        assert_eq!(MESSAGE, message.raw_bytes());
+        assert_eq!(b"body", message.body().unwrap());
+    }
+
+    #[test]
+    fn message_from_owned_send_reuses_body() {
+        let message =
+            MessageFrame::from_owned_send(send_frame(SEND), "m-1".to_owned(), "s-1".to_owned());
+
+        assert_eq!(MESSAGE, message.raw_bytes());
+        assert_eq!("m-1", message.message_id().value());
+        assert_eq!(b"body", message.body().unwrap());
+    }
+
+    #[test]
+    fn message_from_owned_send_without_body() {
+        let send = SendFrame::builder().destination("a".to_owned()).build();
+
+        let message = MessageFrame::from_owned_send(send, "m-1".to_owned(), "s-1".to_owned());
+
+        assert_eq!(
+            b"MESSAGE\nmessage-id:m-1\ndestination:a\nsubscription:s-1\ncontent-length:0\n\n\x00",
+            message.raw_bytes()
+        );
+        assert_eq!(Some(&b""[..]), message.body());
+    }
+
+    #[test]
+    fn message_from_send_corrects_lenient_content_length() {
+        let send = send_frame(b"SEND\ndestination:a\ncontent-length:2\n\nbody\x00");
+        let expected = b"MESSAGE\nmessage-id:m-1\ndestination:a\nsubscription:s-1\ncontent-length:4\n\nbody\x00";
+
+        let message = MessageFrame::from_send(&send, "m-1".to_owned(), "s-1".to_owned());
+        assert_eq!(expected, message.raw_bytes());
+
+        let message = MessageFrame::from_owned_send(send, "m-1".to_owned(), "s-1".to_owned());
+        assert_eq!(expected, message.raw_bytes());
+
+        // The MESSAGE is valid even to a receiver which trusts the declared length
+        assert!(
+            ServerFrame::parse_with(expected.to_vec(), crate::ContentLengthPolicy::Strict).is_ok()
+        );
+    }
+
+    #[test]
+    fn builder_sets_content_length_of_body() {
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .body(b"body".to_vec())
+            .build();
+
+        assert_eq!(Some(&4), frame.content_length().map(|value| value.value()));
+
+        // A value set explicitly is kept
+        let frame = SendFrame::builder()
+            .destination("a".to_owned())
+            .content_length(99)
+            .body(b"body".to_vec())
+            .build();
+
+        assert_eq!(Some(&99), frame.content_length().map(|value| value.value()));
+
+        let frame = SendFrame::builder().destination("a".to_owned()).build();
+
+        assert_eq!(None, frame.content_length());
+    }
+
+    #[test]
+    fn builder_from_lenient_frame_takes_length_of_body() {
+        let send = send_frame(b"SEND\ndestination:a\ncontent-length:2\n\nbody\x00");
+
+        assert_eq!(
+            b"SEND\ndestination:a\ncontent-length:4\n\nbody\x00",
+            SendFrameBuilder::from(&send).build().raw_bytes()
+        );
+    }
+
+    #[test]
+    fn builder_content_length_can_be_set_explicitly() {
+        let builder = SendFrame::builder()
+            .destination("a".to_owned())
+            .auto_content_length(false)
+            .body(b"body".to_vec());
+
+        assert_eq!(None, builder.build().content_length());
+
+        let builder = SendFrame::builder()
+            .destination("a".to_owned())
+            .auto_content_length(false)
+            .content_length(2)
+            .body(b"body".to_vec());
+        let length = builder.encoded_len();
+        let frame = builder.build();
+
+        assert_eq!(Some(&2), frame.content_length().map(|value| value.value()));
+        assert_eq!(length, frame.raw_bytes().len());
+    }
+
+    #[test]
+    fn message_builder_from_send_adds_ack() {
+        let send = send_frame(b"SEND\ndestination:a\nack:forged\n\n\x00");
+        let subscribe = SubscribeFrame::builder()
+            .destination("a".to_owned())
+            .id("s-1".to_owned())
+            .ack_type(AckType::Client)
+            .build();
+        let subscription = ClientAckSubscription::from_subscribe(&subscribe).unwrap();
+
+        let message =
+            MessageFrameBuilder::from_send_to_client_ack(&send, "m-1".to_owned(), &subscription)
+                .ack("a-0".to_owned())
+                .ack("a-1".to_owned())
+                .build();
+
+        assert_eq!(
+            b"MESSAGE\nmessage-id:m-1\ndestination:a\nsubscription:s-1\ncontent-length:0\nack:a-1\n\n\x00",
+            message.raw_bytes()
+        );
+    }
+
+    #[test]
+    fn client_ack_subscription_requires_acknowledgement() {
+        let subscribe = SubscribeFrame::builder()
+            .destination("a".to_owned())
+            .id("s-1".to_owned())
+            .build();
+        assert_eq!(None, ClientAckSubscription::from_subscribe(&subscribe));
+
+        let subscribe = SubscribeFrame::builder()
+            .destination("a".to_owned())
+            .id("s-1".to_owned())
+            .ack_type(AckType::ClientIndividual)
+            .build();
+        let subscription = ClientAckSubscription::from_subscribe(&subscribe).unwrap();
+
+        assert_eq!("s-1", subscription.id());
+        assert_eq!(&AckType::ClientIndividual, subscription.ack_type());
+    }
+
+    #[test]
+    fn named_builder_sets_required_headers_in_any_order() {
+        let frame = MessageFrame::builder()
+            .subscription("s-1".to_owned())
+            .content_type("text/plain".to_owned())
+            .destination("a".to_owned())
+            .message_id("m-1".to_owned())
+            .body(b"body".to_vec())
+            .build();
+
+        let expected = MessageFrame::builder()
+            .message_id("m-1".to_owned())
+            .destination("a".to_owned())
+            .subscription("s-1".to_owned())
+            .content_type("text/plain".to_owned())
+            .body(b"body".to_vec())
+            .build();
+
+        assert_eq!(expected.raw_bytes(), frame.raw_bytes());
+    }
+
+    #[test]
+    fn builder_encodes_into_buffer() {
+        let builder = SendFrame::builder()
+            .destination("stairway:to/heaven".to_owned())
+            .content_type("text/plain".to_owned())
+            .add_custom_header("fo:o".to_owned(), "bar".to_owned())
+            .body(b"Lorem ipsum".to_vec());
+
+        let mut bytes = b"CONNECT\nhost:foo\naccept-version:1.2\n\n\x00".to_vec();
+        let prefix = bytes.len();
+
+        builder.encode_into(&mut bytes);
+
+        assert_eq!(builder.encoded_len(), bytes.len() - prefix);
+        assert_eq!(Vec::<u8>::from(builder.build()), &bytes[prefix..]);
+    }
+
+    #[test]
+    fn frames_encode_into_buffer_without_consuming() {
+        let first = ClientFrame::try_from(b"SEND\ndestination:foo\n\nbar\x00".to_vec()).unwrap();
+        let second =
+            ClientFrame::try_from(b"SUBSCRIBE\ndestination:foo\nid:1\n\n\x00".to_vec()).unwrap();
+
+        let mut bytes = Vec::with_capacity(first.encoded_len() + second.encoded_len());
+        first.encode_into(&mut bytes);
+        second.encode_into(&mut bytes);
+
+        assert_eq!(
+            b"SEND\ndestination:foo\n\nbar\x00SUBSCRIBE\ndestination:foo\nid:1\n\n\x00".to_vec(),
+            bytes
+        );
+        assert!(matches!(first, ClientFrame::Send(_)));
+    }
+
     #[test]
     fn builds_connected_frame() {
-        let frame = ConnectedFrameBuilder::new(StompVersion::V1_1)
+        let frame = ConnectedFrame::builder()
+            .version(StompVersion::V1_1)
             .heartbeat(HeartBeatIntervals {
                 supplied: 20,
                 expected: 10,
@@ -259,7 +1164,8 @@ mod test {
 
     #[test]
     fn writes_connected_frame() {
-        let frame = ConnectedFrameBuilder::new(StompVersion::V1_1)
+        let frame = ConnectedFrame::builder()
+            .version(StompVersion::V1_1)
             .heartbeat(HeartBeatIntervals {
                 supplied: 20,
                 expected: 10,
@@ -276,7 +1182,9 @@ mod test {
 
     #[test]
     fn builds_receipt_frame() {
-        let frame = ReceiptFrameBuilder::new("rcpt-1".to_owned()).build();
+        let frame = ReceiptFrame::builder()
+            .receipt_id("rcpt-1".to_owned())
+            .build();
 
         assert_eq!("rcpt-1", frame.receipt_id().value());
     }
@@ -285,14 +1193,13 @@ mod test {
     fn writes_message_frame() {
         let body = b"Lorem ipsum dolor sit amet,".to_vec();
 
-        let frame = MessageFrameBuilder::new(
-            "msg-1".to_owned(),
-            "path/to/hell".to_owned(),
-            "annual".to_owned(),
-        )
-        .content_type("foo/bar".to_owned())
-        .body(body)
-        .build();
+        let frame = MessageFrame::builder()
+            .message_id("msg-1".to_owned())
+            .destination("path/to/hell".to_owned())
+            .subscription("annual".to_owned())
+            .content_type("foo/bar".to_owned())
+            .body(body)
+            .build();
 
         assert_message_frame_roundtrip(
             frame,
@@ -300,7 +1207,7 @@ mod test {
             "path/to/hell",
             "annual",
             Some("foo/bar"),
-            None,
+            Some(27),
             &vec![],
             Some(b"Lorem ipsum dolor sit amet,"),
         );
@@ -310,15 +1217,14 @@ mod test {
     fn writes_custom_headers() {
         let body = b"Lorem ipsum dolor sit amet,".to_vec();
 
-        let frame = MessageFrameBuilder::new(
-            "msg-1".to_owned(),
-            "path/to/hell".to_owned(),
-            "annual".to_owned(),
-        )
-        .content_type("foo/bar".to_owned())
-        .add_custom_header("hello".to_owned(), "world".to_owned())
-        .body(body)
-        .build();
+        let frame = MessageFrame::builder()
+            .message_id("msg-1".to_owned())
+            .destination("path/to/hell".to_owned())
+            .subscription("annual".to_owned())
+            .content_type("foo/bar".to_owned())
+            .add_custom_header("hello".to_owned(), "world".to_owned())
+            .body(body)
+            .build();
 
         assert_message_frame_roundtrip(
             frame,
@@ -326,12 +1232,13 @@ mod test {
             "path/to/hell",
             "annual",
             Some("foo/bar"),
-            None,
+            Some(27),
             &vec![("hello", "world")],
             Some(b"Lorem ipsum dolor sit amet,"),
         );
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn assert_message_frame_roundtrip(
         frame: MessageFrame,
         expected_id: &str,
@@ -353,7 +1260,7 @@ mod test {
             expected_body,
         );
 
-        let bytes: Vec<u8> = frame.try_into().expect("Error writing bytes");
+        let bytes: Vec<u8> = frame.into();
 
         if let Ok(ServerFrame::Message(frame)) = ServerFrame::try_from(bytes) {
             assert_message_frame(
@@ -371,6 +1278,7 @@ mod test {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn assert_message_frame(
         frame: &MessageFrame,
         expected_id: &str,
@@ -427,14 +1335,13 @@ mod test {
     fn writes_binary_message_frame() {
         let body = vec![0, 1, 1, 2, 3, 5, 8, 13];
 
-        let frame = MessageFrameBuilder::new(
-            "msg-1".to_owned(),
-            "path/to/hell".to_owned(),
-            "annual".to_owned(),
-        )
-        .content_type("foo/bar".to_owned())
-        .body(body)
-        .build();
+        let frame = MessageFrame::builder()
+            .message_id("msg-1".to_owned())
+            .destination("path/to/hell".to_owned())
+            .subscription("annual".to_owned())
+            .content_type("foo/bar".to_owned())
+            .body(body)
+            .build();
 
         assert_message_frame_roundtrip(
             frame,
@@ -442,7 +1349,7 @@ mod test {
             "path/to/hell",
             "annual",
             Some("foo/bar"),
-            None,
+            Some(8),
             &vec![],
             Some(&[0, 1, 1, 2, 3, 5, 8, 13]),
         );
@@ -523,7 +1430,7 @@ mod test {
             );
 
             assert_eq!("stairway/to/heaven", frame.destination().value());
-            return frame.body().unwrap().as_ptr() as u64;
+            frame.body().unwrap().as_ptr() as u64
         });
 
         let Ok(address) = handle.join() else {
@@ -0,0 +1,355 @@
+//! Implements a frame model which is not tied to the commands and headers of the specification, for vendor-specific
+//! commands and for tooling which handles frames generically.
+//!
+//! ```
+//! use stomp_parser::raw::RawFrame;
+//! use std::convert::TryFrom;
+//!
+//! let frame = RawFrame::try_from(b"NOTIFY\ntopic:news\n\nextra, extra\x00".to_vec()).unwrap();
+//!
+//! assert_eq!("NOTIFY", frame.command());
+//! assert_eq!("news", *frame.header("topic").unwrap().value());
+//! assert_eq!(b"extra, extra", frame.body());
+//! ```
+use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
+
+use crate::client::ClientFrame;
+use crate::common::functions::{
+    rebase, rebase_str, write_command, write_encoded_header, write_frame_end, write_headers_end,
+};
+use crate::error::StompParseError;
+use crate::frame::{debug_frame, display_frame, eq_frames, hash_frame, Frame};
+use crate::headers::{ContentLengthValue, CustomValue, DecodableValue};
+use crate::parser::headers::HeaderLines;
+use crate::parser::{command_line, remaining_without_null, ContentLengthPolicy};
+use crate::server::ServerFrame;
+use nom::error::VerboseError;
+
+/// A frame with any command, the headers in the order in which they occur, and the body.
+///
+/// Any syntactically valid frame can be parsed as a `RawFrame`. As with the typed frames, the bytes are kept, so that
+/// converting to bytes, or to and from [`ClientFrame`] and [`ServerFrame`], is lossless.
+pub struct RawFrame {
+    raw: Vec<u8>,
+    command: &'static str,
+    headers: Vec<CustomValue>,
+    body: &'static [u8],
+}
+
+impl RawFrame {
+    /// Creates the frame with the command, headers and body. Names and values of headers are escaped as
+    /// required; the command must be a single non-empty line.
+    pub fn new(
+        command: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Result<Self, StompParseError> {
+        let mut bytes = Vec::new();
+
+        write_command(&mut bytes, command);
+        headers.iter().for_each(|(name, value)| {
+            write_encoded_header(&mut bytes, name, value);
+        });
+        write_headers_end(&mut bytes);
+        bytes.extend_from_slice(body);
+        write_frame_end(&mut bytes);
+
+        let frame = RawFrame::try_from(bytes)?;
+
+        if frame.command == command {
+            Ok(frame)
+        } else {
+            Err(StompParseError::new(format!(
+                "Invalid command {:?}",
+                command
+            )))
+        }
+    }
+
+    /// Parses a frame as `try_from` does, but validates a `content-length` header according to the policy.
+    pub fn parse_with(
+        bytes: Vec<u8>,
+        policy: ContentLengthPolicy,
+    ) -> Result<Self, StompParseError> {
+        let input: *const [u8] = bytes.as_slice();
+        // The slices point into the heap buffer of `bytes`, which moves into the frame unchanged
+        let input = unsafe { input.as_ref().unwrap() };
+
+        let (command, headers, body) = split(input)?;
+        let headers = headers
+            .into_iter()
+            .map(|(name, value)| CustomValue::new(name, value))
+            .collect();
+
+        let frame = RawFrame {
+            raw: bytes,
+            command,
+            headers,
+            body,
+        };
+
+        if let Some(length) = frame.header(ContentLengthValue::NAME) {
+            let length = ContentLengthValue::from_str(length.value())?;
+            policy.check(*length.value(), frame.body.len())?;
+        }
+
+        Ok(frame)
+    }
+
+    /// The command of the frame.
+    pub fn command(&self) -> &str {
+        self.command
+    }
+
+    /// The headers, in the order in which they occur, including repeated ones. Names and values are as they are in
+    /// the frame; `decoded_name` and `decoded_value` undo the escaping.
+    pub fn headers(&self) -> &[CustomValue] {
+        &self.headers
+    }
+
+    /// The first header with the name, which, as the specification requires, is the one that applies when a header
+    /// is repeated.
+    pub fn header(&self, name: &str) -> Option<&CustomValue> {
+        self.headers.iter().find(|header| {
+            header.decoded_name().is_ok_and(|decoded| {
+                decoded.either(|decoded| decoded == name, |decoded| decoded == name)
+            })
+        })
+    }
+
+    /// The value of the first header with the name, decoded.
+    pub fn decoded_header(&self, name: &str) -> Option<Result<String, StompParseError>> {
+        self.header(name)
+            .map(|header| header.decoded_value().map(|value| value.to_string()))
+    }
+
+    /// The body, which is empty if the frame has none.
+    pub fn body(&self) -> &[u8] {
+        self.body
+    }
+
+    /// The bytes of this frame. For a parsed frame, these are exactly the bytes it was parsed from.
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+}
+
+/// The command, the names and values of the headers in order, and the body of a frame.
+pub(crate) type Parts<'a> = (&'a str, Vec<(&'a str, &'a str)>, &'a [u8]);
+
+/// Splits a frame into its parts, without interpreting any of them.
+pub(crate) fn split(input: &[u8]) -> Result<Parts<'_>, StompParseError> {
+    let (input, command) = command_line::<VerboseError<&[u8]>, StompParseError>(input)
+        .map_err(|_| StompParseError::new("Error parsing frame"))?;
+    let command = std::str::from_utf8(command)
+        .map_err(|_| StompParseError::new("badly formed command string, not utf8"))?;
+
+    let mut lines = HeaderLines::new(input);
+    let headers = (&mut lines).collect::<Result<Vec<(&str, &str)>, StompParseError>>()?;
+
+    let (_, body) = remaining_without_null::<VerboseError<&[u8]>>(lines.remaining())
+        .map_err(|_| StompParseError::new("Error parsing frame"))?;
+
+    Ok((command, headers, body))
+}
+
+/// Parses a `RawFrame` from the data contained in the provided vector of bytes.
+impl TryFrom<Vec<u8>> for RawFrame {
+    type Error = StompParseError;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self, StompParseError> {
+        RawFrame::parse_with(bytes, ContentLengthPolicy::default())
+    }
+}
+
+/// Clones the frame, with its command, headers and body pointing into the buffer of the clone.
+impl Clone for RawFrame {
+    fn clone(&self) -> Self {
+        let raw = self.raw.clone();
+
+        let ptr: *const [u8] = raw.as_slice();
+        // The buffer does not move when the clone does, so its slices remain valid
+        let slice = unsafe { ptr.as_ref().unwrap() };
+
+        // The slices are rebased onto a clone of their own buffer, so it is byte-identical to it
+        RawFrame {
+            command: unsafe { rebase_str(self.command, &self.raw, slice) },
+            headers: self
+                .headers
+                .iter()
+                .map(|header| unsafe { header.rebased(&self.raw, slice) })
+                .collect(),
+            body: rebase(self.body, &self.raw, slice),
+            raw,
+        }
+    }
+}
+
+/// Frames are equal if they have the same command, the same headers, decoded and in the same order, and the same
+/// body, just as typed frames are compared.
+impl PartialEq for RawFrame {
+    fn eq(&self, other: &Self) -> bool {
+        eq_frames(self, other)
+    }
+}
+
+impl Eq for RawFrame {}
+
+impl Hash for RawFrame {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_frame(self, state);
+    }
+}
+
+impl crate::__private::Sealed for RawFrame {}
+
+impl Frame for RawFrame {
+    fn raw_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    fn into_raw(self) -> Vec<u8> {
+        self.raw
+    }
+}
+
+/// This implementation serialises [`RawFrame`] into a byte array.
+impl From<RawFrame> for Vec<u8> {
+    fn from(frame: RawFrame) -> Vec<u8> {
+        frame.raw
+    }
+}
+
+impl From<ClientFrame> for RawFrame {
+    fn from(frame: ClientFrame) -> Self {
+        RawFrame::try_from(Vec::from(frame)).expect("Should never fail, because a valid frame")
+    }
+}
+
+impl From<ServerFrame> for RawFrame {
+    fn from(frame: ServerFrame) -> Self {
+        RawFrame::try_from(Vec::from(frame)).expect("Should never fail, because a valid frame")
+    }
+}
+
+/// Parses the frame as a [`ClientFrame`], which fails if the command is not one a client can send, or the headers or
+/// body do not suit it.
+impl TryFrom<RawFrame> for ClientFrame {
+    type Error = StompParseError;
+
+    fn try_from(frame: RawFrame) -> Result<Self, StompParseError> {
+        ClientFrame::try_from(frame.raw)
+    }
+}
+
+/// Parses the frame as a [`ServerFrame`], which fails if the command is not one a server can send, or the headers or
+/// body do not suit it.
+impl TryFrom<RawFrame> for ServerFrame {
+    type Error = StompParseError;
+
+    fn try_from(frame: RawFrame) -> Result<Self, StompParseError> {
+        ServerFrame::try_from(frame.raw)
+    }
+}
+
+impl std::fmt::Debug for RawFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        debug_frame(self, f)
+    }
+}
+
+impl std::fmt::Display for RawFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        display_frame(self, f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::SendFrame;
+    use crate::headers::HeaderValue;
+
+    #[test]
+    fn parses_unknown_commands_with_repeated_headers() {
+        let frame =
+            RawFrame::try_from(b"NOTIFY\nfoo:1\nb\\car:a\\nb\nfoo:2\n\nbody\x00".to_vec()).unwrap();
+
+        assert_eq!("NOTIFY", frame.command());
+        assert_eq!(
+            vec![("foo", "1"), ("b\\car", "a\\nb"), ("foo", "2")],
+            frame
+                .headers()
+                .iter()
+                .map(|header| (header.header_name(), *header.value()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("1", *frame.header("foo").unwrap().value());
+        assert_eq!("a\nb", frame.decoded_header("b:ar").unwrap().unwrap());
+        assert_eq!(b"body", frame.body());
+    }
+
+    #[test]
+    fn clones_are_equal_and_independent() {
+        let frame = RawFrame::try_from(b"NOTIFY\nfoo:1\n\nbody\x00".to_vec()).unwrap();
+        let clone = frame.clone();
+        drop(frame);
+
+        assert_eq!("NOTIFY", clone.command());
+        assert_eq!("1", *clone.header("foo").unwrap().value());
+        assert_eq!(b"body", clone.body());
+        assert_eq!(
+            clone,
+            RawFrame::new("NOTIFY", &[("foo", "1")], b"body").unwrap()
+        );
+        assert_ne!(clone, RawFrame::new("NOTIFY", &[], b"body").unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        assert!(RawFrame::try_from(b"\nfoo:1\n\n\x00".to_vec()).is_err());
+        assert!(RawFrame::try_from(b"NOTIFY\nfoo\n\n\x00".to_vec()).is_err());
+        assert!(RawFrame::try_from(b"NOTIFY\nfoo:1\n\n".to_vec()).is_err());
+        assert!(RawFrame::try_from(b"NOTIFY\ncontent-length:5\n\nbody\x00".to_vec()).is_err());
+        assert!(RawFrame::parse_with(
+            b"NOTIFY\ncontent-length:2\n\nbody\x00".to_vec(),
+            ContentLengthPolicy::Strict
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn new_encodes_headers() {
+        let frame = RawFrame::new("NOTIFY", &[("a:b", "c\nd"), ("a:b", "e")], b"body").unwrap();
+
+        assert_eq!(
+            b"NOTIFY\na\\cb:c\\nd\na\\cb:e\n\nbody\x00".to_vec(),
+            Vec::from(frame)
+        );
+        assert!(RawFrame::new("NO\nTIFY", &[], b"").is_err());
+        assert!(RawFrame::new("", &[], b"").is_err());
+    }
+
+    #[test]
+    fn converts_losslessly_to_and_from_typed_frames() {
+        let bytes = b"SEND\r\ndestination:a\r\nfoo:bar\nfoo:baz\n\nbody\x00".to_vec();
+
+        let frame = RawFrame::try_from(bytes.clone()).unwrap();
+        let frame = ClientFrame::try_from(frame).unwrap();
+        assert_eq!(bytes, frame.raw_bytes());
+
+        let frame = RawFrame::from(frame);
+        assert_eq!("SEND", frame.command());
+        assert_eq!(bytes, frame.raw_bytes());
+
+        assert!(ServerFrame::try_from(frame).is_err());
+
+        let send = SendFrame::builder().destination("a".to_owned()).build();
+        let expected = send.raw_bytes().to_vec();
+        assert_eq!(
+            expected,
+            RawFrame::from(ClientFrame::Send(send)).raw_bytes()
+        );
+    }
+}
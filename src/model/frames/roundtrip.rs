@@ -0,0 +1,420 @@
+//! Property-based tests which build every frame type from random header values, custom headers and
+//! bodies, serialise it, parse it back, and compare the parsed frame field by field with the input.
+use std::convert::TryFrom;
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+
+use super::client::*;
+use super::server::*;
+use crate::model::headers::*;
+
+/// Any string, including the characters which must be escaped.
+fn value() -> impl Strategy<Value = String> {
+    prop_oneof![
+        any::<String>(),
+        "[a-z:\\\\\r\n/.-]{0,16}",
+        Just(String::new())
+    ]
+}
+
+/// Names for custom headers, which can't be empty nor be mistaken for a known header.
+fn custom_headers() -> impl Strategy<Value = Vec<(String, String)>> {
+    vec(
+        (value().prop_map(|name| format!("x-{}", name)), value()),
+        0..4,
+    )
+}
+
+fn body() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<u8>(), 0..64)
+}
+
+fn heartbeat() -> impl Strategy<Value = HeartBeatIntervals> {
+    (any::<u32>(), any::<u32>())
+        .prop_map(|(supplied, expected)| HeartBeatIntervals::new(supplied, expected))
+}
+
+/// The known versions, and unknown ones, which may need escaping but never contain the separating comma.
+fn version() -> impl Strategy<Value = StompVersion> {
+    prop_oneof![
+        Just(StompVersion::V1_0),
+        Just(StompVersion::V1_1),
+        Just(StompVersion::V1_2),
+        "[a-z0-9:\\\\\r\n.-]{1,8}".prop_map(|version| version.parse().unwrap())
+    ]
+}
+
+fn versions() -> impl Strategy<Value = StompVersions> {
+    vec(version(), 1..4).prop_map(StompVersions)
+}
+
+fn ack_type() -> impl Strategy<Value = AckType> {
+    prop_oneof![
+        Just(AckType::Auto),
+        Just(AckType::Client),
+        Just(AckType::ClientIndividual)
+    ]
+}
+
+fn decoded<V: DecodableValue>(value: &V) -> String {
+    value
+        .decoded_value()
+        .expect("Header value should decode")
+        .to_string()
+}
+
+fn decoded_opt<V: DecodableValue>(value: Option<&V>) -> Option<String> {
+    value.map(decoded)
+}
+
+fn decoded_custom(custom: &[CustomValue]) -> Vec<(String, String)> {
+    custom
+        .iter()
+        .map(|header| {
+            (
+                header
+                    .decoded_name()
+                    .expect("Header name should decode")
+                    .to_string(),
+                decoded(header),
+            )
+        })
+        .collect()
+}
+
+fn client_roundtrip<F: Into<Vec<u8>>>(frame: F) -> ClientFrame {
+    let bytes: Vec<u8> = frame.into();
+    ClientFrame::try_from(bytes.clone()).unwrap_or_else(|error| {
+        panic!(
+            "Error '{}' parsing {:?}",
+            error.message(),
+            String::from_utf8_lossy(&bytes)
+        )
+    })
+}
+
+fn server_roundtrip<F: Into<Vec<u8>>>(frame: F) -> ServerFrame {
+    let bytes: Vec<u8> = frame.into();
+    ServerFrame::try_from(bytes.clone()).unwrap_or_else(|error| {
+        panic!(
+            "Error '{}' parsing {:?}",
+            error.message(),
+            String::from_utf8_lossy(&bytes)
+        )
+    })
+}
+
+proptest! {
+    #[test]
+    fn abort_frame_roundtrips(transaction in value()) {
+        let frame = AbortFrame::builder().transaction(transaction.clone()).build();
+
+        let ClientFrame::Abort(frame) = client_roundtrip(frame) else {
+            panic!("Not an Abort frame")
+        };
+
+        prop_assert_eq!(transaction, decoded(frame.transaction()));
+    }
+
+    #[test]
+    fn ack_frame_roundtrips(id in value(), transaction in value(), receipt in option::of(value())) {
+        let mut builder = AckFrame::builder().id(id.clone()).transaction(transaction.clone());
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+
+        let ClientFrame::Ack(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not an Ack frame")
+        };
+
+        prop_assert_eq!(id, decoded(frame.id()));
+        prop_assert_eq!(transaction, decoded(frame.transaction()));
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+    }
+
+    #[test]
+    fn begin_frame_roundtrips(transaction in value(), receipt in option::of(value())) {
+        let mut builder = BeginFrame::builder().transaction(transaction.clone());
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+
+        let ClientFrame::Begin(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not a Begin frame")
+        };
+
+        prop_assert_eq!(transaction, decoded(frame.transaction()));
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+    }
+
+    #[test]
+    fn commit_frame_roundtrips(transaction in value(), receipt in option::of(value())) {
+        let mut builder = CommitFrame::builder().transaction(transaction.clone());
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+
+        let ClientFrame::Commit(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not a Commit frame")
+        };
+
+        prop_assert_eq!(transaction, decoded(frame.transaction()));
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+    }
+
+    #[test]
+    fn connect_frame_roundtrips(
+        host in value(),
+        accept_version in versions(),
+        heartbeat in option::of(heartbeat()),
+        login in option::of(value()),
+        passcode in option::of(value()),
+        custom in custom_headers()
+    ) {
+        let mut builder = ConnectFrame::builder().host(host.clone()).accept_version(accept_version.clone());
+        if let Some(heartbeat) = heartbeat.clone() {
+            builder = builder.heartbeat(heartbeat);
+        }
+        if let Some(login) = login.clone() {
+            builder = builder.login(login);
+        }
+        if let Some(passcode) = passcode.clone() {
+            builder = builder.passcode(passcode);
+        }
+        for (name, value) in custom.iter().cloned() {
+            builder = builder.add_custom_header(name, value);
+        }
+
+        let ClientFrame::Connect(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not a Connect frame")
+        };
+
+        prop_assert_eq!(host, decoded(frame.host()));
+        prop_assert_eq!(&accept_version, frame.accept_version().value());
+        prop_assert_eq!(&heartbeat.unwrap_or_default(), frame.heartbeat().value());
+        prop_assert_eq!(login, decoded_opt(frame.login()));
+        prop_assert_eq!(passcode, decoded_opt(frame.passcode()));
+        prop_assert_eq!(custom, decoded_custom(&frame.custom));
+        prop_assert_eq!(frame.raw_bytes(), Vec::from(frame.to_builder().build()));
+    }
+
+    #[test]
+    fn disconnect_frame_roundtrips(receipt in value()) {
+        let frame = DisconnectFrame::builder().receipt(receipt.clone()).build();
+
+        let ClientFrame::Disconnect(frame) = client_roundtrip(frame) else {
+            panic!("Not a Disconnect frame")
+        };
+
+        prop_assert_eq!(receipt, decoded(frame.receipt()));
+    }
+
+    #[test]
+    fn nack_frame_roundtrips(id in value(), transaction in value(), receipt in option::of(value())) {
+        let mut builder = NackFrame::builder().id(id.clone()).transaction(transaction.clone());
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+
+        let ClientFrame::Nack(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not a Nack frame")
+        };
+
+        prop_assert_eq!(id, decoded(frame.id()));
+        prop_assert_eq!(transaction, decoded(frame.transaction()));
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+    }
+
+    #[test]
+    fn send_frame_roundtrips(
+        destination in value(),
+        content_type in option::of(value()),
+        with_content_length in any::<bool>(),
+        transaction in option::of(value()),
+        receipt in option::of(value()),
+        custom in custom_headers(),
+        body in body()
+    ) {
+        let content_length = Some(body.len() as u32).filter(|_| with_content_length);
+
+        let mut builder = SendFrame::builder().destination(destination.clone());
+        if let Some(content_type) = content_type.clone() {
+            builder = builder.content_type(content_type);
+        }
+        builder = builder.auto_content_length(with_content_length);
+        if let Some(transaction) = transaction.clone() {
+            builder = builder.transaction(transaction);
+        }
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+        for (name, value) in custom.iter().cloned() {
+            builder = builder.add_custom_header(name, value);
+        }
+        builder = builder.body(body.clone());
+
+        let ClientFrame::Send(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not a Send frame")
+        };
+
+        prop_assert_eq!(destination, decoded(frame.destination()));
+        prop_assert_eq!(content_type, decoded_opt(frame.content_type()));
+        prop_assert_eq!(content_length, frame.content_length().map(|value| *value.value()));
+        prop_assert_eq!(transaction, decoded_opt(frame.transaction()));
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+        prop_assert_eq!(custom, decoded_custom(&frame.custom));
+        prop_assert_eq!(Some(body.as_slice()), frame.body());
+        prop_assert_eq!(frame.raw_bytes(), Vec::from(frame.to_builder().build()));
+    }
+
+    #[test]
+    fn subscribe_frame_roundtrips(
+        destination in value(),
+        id in value(),
+        ack_type in option::of(ack_type()),
+        receipt in option::of(value()),
+        custom in custom_headers()
+    ) {
+        let mut builder = SubscribeFrame::builder().destination(destination.clone()).id(id.clone());
+        if let Some(ack_type) = ack_type.clone() {
+            builder = builder.ack_type(ack_type);
+        }
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+        for (name, value) in custom.iter().cloned() {
+            builder = builder.add_custom_header(name, value);
+        }
+
+        let ClientFrame::Subscribe(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not a Subscribe frame")
+        };
+
+        prop_assert_eq!(destination, decoded(frame.destination()));
+        prop_assert_eq!(id, decoded(frame.id()));
+        prop_assert_eq!(&ack_type.unwrap_or_default(), frame.ack_type().value());
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+        prop_assert_eq!(custom, decoded_custom(&frame.custom));
+    }
+
+    #[test]
+    fn unsubscribe_frame_roundtrips(id in value(), receipt in option::of(value())) {
+        let mut builder = UnsubscribeFrame::builder().id(id.clone());
+        if let Some(receipt) = receipt.clone() {
+            builder = builder.receipt(receipt);
+        }
+
+        let ClientFrame::Unsubscribe(frame) = client_roundtrip(builder.build()) else {
+            panic!("Not an Unsubscribe frame")
+        };
+
+        prop_assert_eq!(id, decoded(frame.id()));
+        prop_assert_eq!(receipt, decoded_opt(frame.receipt()));
+    }
+
+    #[test]
+    fn connected_frame_roundtrips(
+        version in version(),
+        heartbeat in option::of(heartbeat()),
+        session in option::of(value()),
+        server in option::of(value())
+    ) {
+        let mut builder = ConnectedFrame::builder().version(version.clone());
+        if let Some(heartbeat) = heartbeat.clone() {
+            builder = builder.heartbeat(heartbeat);
+        }
+        if let Some(session) = session.clone() {
+            builder = builder.session(session);
+        }
+        if let Some(server) = server.clone() {
+            builder = builder.server(server);
+        }
+
+        let ServerFrame::Connected(frame) = server_roundtrip(builder.build()) else {
+            panic!("Not a Connected frame")
+        };
+
+        prop_assert_eq!(&version, frame.version().value());
+        prop_assert_eq!(heartbeat.as_ref(), frame.heartbeat().map(|value| value.value()));
+        prop_assert_eq!(session, decoded_opt(frame.session()));
+        prop_assert_eq!(server, decoded_opt(frame.server()));
+    }
+
+    #[test]
+    fn receipt_frame_roundtrips(receipt_id in value()) {
+        let frame = ReceiptFrame::builder().receipt_id(receipt_id.clone()).build();
+
+        let ServerFrame::Receipt(frame) = server_roundtrip(frame) else {
+            panic!("Not a Receipt frame")
+        };
+
+        prop_assert_eq!(receipt_id, decoded(frame.receipt_id()));
+    }
+
+    #[test]
+    fn error_frame_roundtrips(
+        message in option::of(value()),
+        content_type in option::of(value()),
+        custom in custom_headers(),
+        body in body()
+    ) {
+        let mut builder = ErrorFrame::builder();
+        if let Some(message) = message.clone() {
+            builder = builder.message(message);
+        }
+        if let Some(content_type) = content_type.clone() {
+            builder = builder.content_type(content_type);
+        }
+        for (name, value) in custom.iter().cloned() {
+            builder = builder.add_custom_header(name, value);
+        }
+        builder = builder.body(body.clone());
+
+        let ServerFrame::Error(frame) = server_roundtrip(builder.build()) else {
+            panic!("Not an Error frame")
+        };
+
+        prop_assert_eq!(message, decoded_opt(frame.message()));
+        prop_assert_eq!(content_type, decoded_opt(frame.content_type()));
+        prop_assert_eq!(custom, decoded_custom(&frame.custom));
+        prop_assert_eq!(Some(body.as_slice()), frame.body());
+    }
+
+    #[test]
+    fn message_frame_roundtrips(
+        message_id in value(),
+        destination in value(),
+        subscription in value(),
+        content_type in option::of(value()),
+        with_content_length in any::<bool>(),
+        custom in custom_headers(),
+        body in body()
+    ) {
+        let content_length = Some(body.len() as u32).filter(|_| with_content_length);
+
+        let mut builder = MessageFrame::builder().message_id(message_id.clone()).destination(destination.clone()).subscription(subscription.clone());
+        if let Some(content_type) = content_type.clone() {
+            builder = builder.content_type(content_type);
+        }
+        builder = builder.auto_content_length(with_content_length);
+        for (name, value) in custom.iter().cloned() {
+            builder = builder.add_custom_header(name, value);
+        }
+        builder = builder.body(body.clone());
+
+        let ServerFrame::Message(frame) = server_roundtrip(builder.build()) else {
+            panic!("Not a Message frame")
+        };
+
+        prop_assert_eq!(message_id, decoded(frame.message_id()));
+        prop_assert_eq!(destination, decoded(frame.destination()));
+        prop_assert_eq!(subscription, decoded(frame.subscription()));
+        prop_assert_eq!(content_type, decoded_opt(frame.content_type()));
+        prop_assert_eq!(content_length, frame.content_length().map(|value| *value.value()));
+        prop_assert_eq!(custom, decoded_custom(&frame.custom));
+        prop_assert_eq!(Some(body.as_slice()), frame.body());
+        prop_assert_eq!(frame.raw_bytes(), Vec::from(frame.to_builder().build()));
+    }
+}
@@ -1,18 +1,45 @@
-macro_rules! sender_frame {
-    ( $name:ident,  $($comment:literal,)? $command:ident, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt $(: $opt_header_default_comment:literal)?)?  ),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident])?  $(,$long_comment:literal)*) => {
+/// Generates, for each required header of a builder, a named setter which is available while that header is unset, and
+/// records in the type of the builder that it has been set.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_required_setters {
+    ($builder:ident, [$($before:ident : $before_type:ident),*], []) => {};
 
-        paste::paste! {
+    ($builder:ident, [$($before:ident : $before_type:ident),*], [$header_name:ident : $header_type:ident $(, $after:ident : $after_type:ident)*]) => {
+        $crate::__private::paste::paste! {
+            impl<$([<$before_type State>],)* $([<$after_type State>]),*> $builder<$([<$before_type State>],)* $crate::typestate::Unset $(, [<$after_type State>])*> {
+                #[doc = "The value of the `"$header_name"` header."]
+                pub fn $header_name(mut self, new_val: <$crate::headers::[<$header_type Value>]<'static> as $crate::headers::HeaderValue>::OwnedValue) -> $builder<$([<$before_type State>],)* $crate::typestate::Set $(, [<$after_type State>])*> {
+                    self.$header_name = Some(new_val);
+                    self.retype()
+                }
+            }
+        }
+
+        $crate::__stomp_required_setters!($builder, [$($before : $before_type,)* $header_name : $header_type], [$($after : $after_type),*]);
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_sender_frame {
+    ( $name:ident,  $($comment:literal,)? $command:ident, $origin:ident $(, $header_name:ident : $header_type:ident )* $(,( $(  $opt_header_name:ident : $opt_header_type:ident $(: $opt_header_default:tt $(: $opt_header_default_comment:literal)?)?  ),* ))? $(,[custom: $has_custom:ident])? $(,[body: $has_body:ident $(: $length_header:ident)?])?  $(,$long_comment:literal)*) => {
+
+        $crate::__private::paste::paste! {
             $(#[doc = ""$comment]
             #[doc = ""])?
             #[doc = "This frame has required headers "$("`"$header_name"`")","* $(" and optional headers " $("`"$opt_header_name"`")","* )?"."]
+            #[doc = ""]
+            #[doc = "The type parameters record which of the required headers have been set, so that `build` is only"]
+            #[doc = "available once all of them are; see [`"$name"::builder`]."]
             $(#[doc = ""]
             #[doc = ""$long_comment])?
-            pub struct [<$name Builder>] {
+            pub struct [<$name Builder>]<$([<$header_type State>] = $crate::typestate::Set),*> {
                 $(
-                    $header_name: <[<$header_type Value>]<'static> as HeaderValue>::OwnedValue,
+                    $header_name: Option<<$crate::headers::[<$header_type Value>]<'static> as $crate::headers::HeaderValue>::OwnedValue>,
                 )*
                 $($(
-                    $opt_header_name: Option<<[<$opt_header_type Value>]<'static> as HeaderValue>::OwnedValue>,
+                    $opt_header_name: Option<<$crate::headers::[<$opt_header_type Value>]<'static> as $crate::headers::HeaderValue>::OwnedValue>,
                 )*)?
                 $(
                     #[doc(hidden)]
@@ -23,14 +50,50 @@ macro_rules! sender_frame {
                     #[doc(hidden)]
                     #[doc = "Useless doc: `"$has_body"`."]
                     body: Option<Vec<u8>>,
+                    $(
+                        #[doc(hidden)]
+                        #[doc = "Useless doc: `"$length_header"`."]
+                        auto_content_length: bool,
+                    )?
                 )?
+                // The decoded text of the typed values of the frame this was created from, by header name
+                spellings: Vec<(&'static str, String)>,
+                state: std::marker::PhantomData<($([<$header_type State>],)*)>,
             }
 
-            impl [<$name Builder>] {
+            impl $name<'static> {
+                #[doc = "A builder with none of the required headers set. Each is set with the method named after it,"]
+                #[doc = "and the frame can only be built once all of them have been."]
+                pub fn builder() -> [<$name Builder>]<$($crate::__stomp_replace_with_type!($header_name, $crate::typestate::Unset)),*> {
+                    [<$name Builder>] {
+                        $(
+                            $header_name: None,
+                        )*
+                        $($(
+                            $opt_header_name: $crate::__stomp_choose_from_presence!($($opt_header_default)? {Some($($opt_header_default)?().into())},{None}),
+                        )*)?
+                        $(
+                            custom: $crate::__stomp_choose_from_presence!($has_custom {Vec::new()}, {Vec::new()}),
+                        )?
+                        $(
+                            body: $crate::__stomp_choose_from_presence!($has_body None, None),
+                            $(
+                                auto_content_length: $crate::__stomp_true_if_present!($length_header),
+                            )?
+                        )?
+                        spellings: Vec::new(),
+                        state: std::marker::PhantomData,
+                    }
+                }
+            }
+
+            $crate::__stomp_required_setters!([<$name Builder>], [], [$($header_name : $header_type),*]);
+
+            impl<$([<$header_type State>]),*> [<$name Builder>]<$([<$header_type State>]),*> {
                 $($(
                     #[doc = "The value of the `"$opt_header_name"` header."]
                     $($(#[doc = "Defaults to `"$opt_header_default_comment"` if not supplied."])?)?
-                    pub fn $opt_header_name(mut self, new_val: <[<$opt_header_type Value>] as HeaderValue>::OwnedValue) -> [<$name Builder>] {
+                    pub fn $opt_header_name(mut self, new_val: <$crate::headers::[<$opt_header_type Value>] as $crate::headers::HeaderValue>::OwnedValue) -> Self {
                         self.$opt_header_name = Some(new_val);
 
                         self
@@ -38,104 +101,270 @@ macro_rules! sender_frame {
                 )*)?
                 $(
                     #[doc = "Useless doc: `"$has_custom"`."]
-                    pub fn add_custom_header(mut self, name: String, value: String) -> [<$name Builder>] {
+                    pub fn add_custom_header(mut self, name: String, value: String) -> Self {
                         self.custom.push((name, value));
                         self
                     }
+
+                    #[doc = "Sets a custom header with a typed value, replacing any custom headers with the same name, so that"]
+                    #[doc = "the value reads back; see [`TypedHeaderValue`]."]
+                    ///
+                    /// # Panics
+                    ///
+                    /// If the `Display` implementation of the value returns an error, as `ToString::to_string` does.
+                    pub fn custom_header<V: $crate::headers::TypedHeaderValue<'static>>(mut self, value: V::OwnedValue) -> Self
+                    where
+                        V::OwnedValue: std::fmt::Display,
+                    {
+                        self.custom.retain(|(name, _)| name != V::NAME);
+                        self.add_custom_header(V::NAME.to_owned(), value.to_string())
+                    }
                 )?
                 $(
                     #[doc = "Useless doc: `"$has_body"`."]
-                    pub fn body(mut self, new_value: Vec<u8>) -> [<$name Builder>] {
+                    pub fn body(mut self, new_value: Vec<u8>) -> Self {
                         self.body = Some(new_value);
                         self
                     }
+
+                    $(
+                        #[doc = "Whether the `"$length_header"` header is set to the length of the body, whenever there is one"]
+                        #[doc = "and the header has not been set explicitly. This is the default, as the specification"]
+                        #[doc = "recommends it."]
+                        pub fn auto_content_length(mut self, enabled: bool) -> Self {
+                            self.auto_content_length = enabled;
+                            self
+                        }
+
+                        fn [<effective_ $length_header>](&self) -> Option<u32> {
+                            match (self.$length_header, self.body.as_ref()) {
+                                (None, Some(body)) if self.auto_content_length => Some(
+                                    <u32 as std::convert::TryFrom<usize>>::try_from(body.len())
+                                        .expect("Bodies of 4 GiB or more are too long for the length header"),
+                                ),
+                                (length, _) => length,
+                            }
+                        }
+                    )?
                 )?
 
-                pub fn new($(
-                            $header_name: <[<$header_type Value>]<'static> as HeaderValue>::OwnedValue,
-                        )*) -> [<$name Builder>] {
+                /// Moves the values into a builder in which the required headers have the given states.
+                fn retype<$([<$header_type Next>]),*>(self) -> [<$name Builder>]<$([<$header_type Next>]),*> {
                     [<$name Builder>] {
                         $(
-                            $header_name,
+                            $header_name: self.$header_name,
                         )*
                         $($(
-                            $opt_header_name: choose_from_presence!($($opt_header_default)? {Some($($opt_header_default)?().into())},{None}),
+                            $opt_header_name: self.$opt_header_name,
                         )*)?
                         $(
-                            custom: choose_from_presence!($has_custom {Vec::new()}, {Vec::new()}),
+                            custom: $crate::__stomp_choose_from_presence!($has_custom {self.custom}, {Vec::new()}),
                         )?
                         $(
-                            body: choose_from_presence!($has_body None, None),
+                            body: $crate::__stomp_choose_from_presence!($has_body {self.body}, {None}),
+                            $(
+                                auto_content_length: $crate::__stomp_choose_from_presence!($length_header {self.auto_content_length}, {true}),
+                            )?
                         )?
+                        spellings: self.spellings,
+                        state: std::marker::PhantomData,
                     }
                 }
+            }
 
+            impl [<$name Builder>] {
+                #[doc = "A builder with the required headers set to the arguments, in the order in which they are listed"]
+                #[doc = "above. Arguments of the same type are easily swapped, so the named setters of [`"$name"::builder`]"]
+                #[doc = "should be used instead."]
+                #[deprecated(note = "use builder() and the named setters")]
+                #[allow(unused_mut)]
+                pub fn new($(
+                            $header_name: <$crate::headers::[<$header_type Value>]<'static> as $crate::headers::HeaderValue>::OwnedValue,
+                        )*) -> [<$name Builder>] {
+                    let mut builder = $name::builder().retype();
+                    $(
+                        builder.$header_name = Some($header_name);
+                    )*
+                    builder
+                }
+            }
+
+            impl<$([<$header_type State>]: $crate::typestate::Provided),*> [<$name Builder>]<$([<$header_type State>]),*> {
+
+                /// The number of bytes to reserve for the frame, which is estimated without formatting the values of the
+                /// typed headers, so that they are only formatted as they are written.
+                fn estimated_len(&self) -> usize {
+                    let mut length = $name::NAME.len() + 2 * $crate::__private::constants::LINE_SEPARATOR.len() + $crate::__private::constants::TERMINATOR.len();
+                    $(
+                        $crate::__stomp_blank!($header_name);
+                        length += $crate::__private::constants::ESTIMATED_HEADER_LEN;
+                    )*
+                    $($(
+                        length += self.$opt_header_name.as_ref().map_or(0, |_| $crate::__private::constants::ESTIMATED_HEADER_LEN);
+                    )*)?
+                    $(
+                        $crate::__stomp_blank!($has_custom);
+                        length += self.custom.iter().map(|(name, value)| name.len() + value.len() + 2).sum::<usize>();
+                    )?
+                    $(
+                        $crate::__stomp_blank!($has_body);
+                        length += self.body.as_ref().map_or(0, |body| body.len() + $crate::__private::constants::ESTIMATED_HEADER_LEN);
+                    )?
+                    length
+                }
+
+                #[doc = "The exact number of bytes that [`"$name"`] built from this builder serialises to."]
+                $($(
+                    #[doc = ""]
+                    #[doc = "# Panics"]
+                    #[doc = ""]
+                    #[doc = "If the `"$length_header"` header is to be set to the length of a body of 4 GiB or more, which it"]
+                    #[doc = "cannot hold."]
+                )?)?
+                pub fn encoded_len(&self) -> usize {
+                    // The length header, if any, is shadowed by its effective value
+                    $($(
+                        #[allow(unused_variables)]
+                        let $opt_header_name = self.$opt_header_name.as_ref();
+                    )*)?
+                    $($(
+                        let effective_length = self.[<effective_ $length_header>]();
+                        let $length_header = effective_length.as_ref();
+                    )?)?
+
+                    let mut length = $name::NAME.len() + $crate::__private::constants::LINE_SEPARATOR.len();
+                    $(
+                        length += $crate::__private::functions::encoded_header_len($crate::headers::[<$header_type Value>]::NAME, &$crate::__private::functions::Spelling::new(&self.spellings, $crate::headers::[<$header_type Value>]::NAME, self.$header_name.as_ref().expect("Required headers are set in this state")));
+                    )*
+                    $($(
+                        length += $opt_header_name.map_or(0, |value| $crate::__private::functions::encoded_header_len($crate::headers::[<$opt_header_type Value>]::NAME, &$crate::__private::functions::Spelling::new(&self.spellings, $crate::headers::[<$opt_header_type Value>]::NAME, value)));
+                    )*)?
+                    $(
+                        $crate::__stomp_blank!($has_custom);
+                        length += self.custom.iter().map(|(name, value)| $crate::__private::functions::encoded_header_len(name, value)).sum::<usize>();
+                    )?
+                    length += $crate::__private::constants::LINE_SEPARATOR.len();
+                    $(
+                        $crate::__stomp_blank!($has_body);
+                        length += self.body.as_ref().map_or(0, |body| body.len());
+                    )?
+                    length + $crate::__private::constants::TERMINATOR.len()
+                }
+
+                #[doc = "Appends the bytes of the [`"$name"`] that would be built from this builder to the buffer, without"]
+                #[doc = "consuming the builder or allocating a frame. This allows many frames to be written to a single buffer."]
+                $($(
+                    #[doc = ""]
+                    #[doc = "# Panics"]
+                    #[doc = ""]
+                    #[doc = "If the `"$length_header"` header is to be set to the length of a body of 4 GiB or more, which it"]
+                    #[doc = "cannot hold."]
+                )?)?
+                pub fn encode_into(&self, bytes: &mut Vec<u8>) {
+                    bytes.reserve(self.estimated_len());
+
+                    // The length header, if any, is shadowed by its effective value
+                    $($(
+                        #[allow(unused_variables)]
+                        let $opt_header_name = self.$opt_header_name.as_ref();
+                    )*)?
+                    $($(
+                        let effective_length = self.[<effective_ $length_header>]();
+                        let $length_header = effective_length.as_ref();
+                    )?)?
+
+                    $crate::__private::functions::write_command(bytes, $name::NAME);
+                    $(
+                        $crate::__private::functions::write_encoded_header(bytes, $crate::headers::[<$header_type Value>]::NAME, &$crate::__private::functions::Spelling::new(&self.spellings, $crate::headers::[<$header_type Value>]::NAME, self.$header_name.as_ref().expect("Required headers are set in this state")));
+                    )*
+                    $($(
+                        if let Some(value) = $opt_header_name {
+                            $crate::__private::functions::write_encoded_header(bytes, $crate::headers::[<$opt_header_type Value>]::NAME, &$crate::__private::functions::Spelling::new(&self.spellings, $crate::headers::[<$opt_header_type Value>]::NAME, value));
+                        }
+                    )*)?
+                    $(
+                        $crate::__stomp_blank!($has_custom);
+                        self.custom.iter().for_each(|(name, value)| {
+                            $crate::__private::functions::write_encoded_header(bytes, name, value);
+                        });
+                    )?
+                    $crate::__private::functions::write_headers_end(bytes);
+                    $(
+                        $crate::__stomp_blank!($has_body);
+                        if let Some(body) = self.body.as_ref() {
+                            bytes.extend_from_slice(body);
+                        }
+                    )?
+                    $crate::__private::functions::write_frame_end(bytes);
+                }
+
+                #[doc = "The [`"$name"`] with the headers and body of this builder."]
+                $($(
+                    #[doc = ""]
+                    #[doc = "# Panics"]
+                    #[doc = ""]
+                    #[doc = "If the `"$length_header"` header is to be set to the length of a body of 4 GiB or more, which it"]
+                    #[doc = "cannot hold."]
+                )?)?
                 #[allow(unused_mut)]
                 pub fn build(mut self) -> $name<'static> {
+                    $($(
+                        self.$length_header = self.[<effective_ $length_header>]();
+                    )?)?
+
                     // First, build the byte array
-                    let mut bytes : Vec<u8> = Vec::with_capacity(1000);
+                    let mut bytes : Vec<u8> = Vec::with_capacity(self.estimated_len());
                     let bytes_ref = &mut bytes;
 
                     let mut frame = $name::init(Vec::new());
 
-                    write_command(bytes_ref, $name::NAME);
+                    $crate::__private::functions::write_command(bytes_ref, $name::NAME);
 
                     $(
-                        // Write the required header, returning an error if the value was not set
-                        let (_,[<$header_name _range>]) = if [<$header_type Value>]::OWNED {
-                            // Owned values are already in the right form for the frame, but also need to be written to the
-                            // output buffer
-                            let mut bytes = self.[<$header_name>].to_string().into_bytes();
-                            frame.$header_name = [<$header_type Value>]::from_owned(self.[<$header_name>]);
-                            write_header(bytes_ref, [<$header_type Value>]::NAME, &mut bytes)
-                        } else {
-                            // Non-owned values strings; the value for the header on the frame needs to be in the byte buffer
-                            let mut bytes = self.[<$header_name>].to_string().into_bytes();
-                            write_header(bytes_ref, [<$header_type Value>]::NAME, &mut bytes)
-                        };
+                        let $header_name = self.$header_name.take().expect("Required headers are set in this state");
+                        let spelling = $crate::__private::functions::Spelling::new(&self.spellings, $crate::headers::[<$header_type Value>]::NAME, &$header_name);
+                        let [<$header_name _spelt>] = spelling.is_spelt();
+                        let (_,[<$header_name _range>]) = $crate::__private::functions::write_encoded_header(bytes_ref, $crate::headers::[<$header_type Value>]::NAME, &spelling);
+                        if <$crate::headers::[<$header_type Value>] as $crate::headers::HeaderValue>::OWNED && ![<$header_name _spelt>] {
+                            // Owned values are already in the right form for the frame
+                            frame.$header_name = $crate::headers::[<$header_type Value>]::from_owned($header_name);
+                        }
                     )*
 
                     $($(
-                        // Write the required header, returning an error if the value was not set
-                        let [<$opt_header_name _range>] = if [<$opt_header_type Value>]::OWNED {
-                            // Owned values are already in the right form for the frame, but also need to be written to the
-                            // output buffer
-                            self.[<$opt_header_name>].take().map(|value| {
-                                let mut bytes = value.to_string().into_bytes();
-                                 choose_from_presence!($($opt_header_default)? {
-                                    frame.$opt_header_name = [<$opt_header_type Value>]::from_owned(value);
+                        let [<$opt_header_name _range>] = self.$opt_header_name.take().map(|value| {
+                            let spelling = $crate::__private::functions::Spelling::new(&self.spellings, $crate::headers::[<$opt_header_type Value>]::NAME, &value);
+                            let spelt = spelling.is_spelt();
+                            let ranges = $crate::__private::functions::write_encoded_header(bytes_ref, $crate::headers::[<$opt_header_type Value>]::NAME, &spelling);
+                            if <$crate::headers::[<$opt_header_type Value>] as $crate::headers::HeaderValue>::OWNED && !spelt {
+                                // Owned values are already in the right form for the frame
+                                $crate::__stomp_choose_from_presence!($($opt_header_default)? {
+                                    frame.$opt_header_name = $crate::headers::[<$opt_header_type Value>]::from_owned(value);
                                 }, {
-                                    frame.$opt_header_name = Some([<$opt_header_type Value>]::from_owned(value));
+                                    frame.$opt_header_name = Some($crate::headers::[<$opt_header_type Value>]::from_owned(value));
                                 });
-                                write_header(bytes_ref, [<$opt_header_type Value>]::NAME, &mut bytes)
-                            })
-                        } else {
-                            // Non-owned values strings; the value for the header on the frame needs to be in the byte buffer
-                            self.[<$opt_header_name>].take().map(|value| {
-                                let mut bytes = value.to_string().into_bytes();
-                                write_header(bytes_ref, [<$opt_header_type Value>]::NAME, &mut bytes)
-                            })
-                        };
+                            }
+                            (ranges, spelt)
+                        });
                     )*)?
 
                     $(
                         let $has_custom : Vec<((usize, usize),(usize,usize))> = self.custom.iter().map(|(name, value)| {
-                             let mut bytes = value.to_string().into_bytes();
-                             write_header(bytes_ref, &name, &mut bytes)
+                             $crate::__private::functions::write_encoded_header(bytes_ref, name, value)
                         }).collect();
                     )?
 
                     // End the headers
-                    write_headers_end(bytes_ref);
+                    $crate::__private::functions::write_headers_end(bytes_ref);
 
                     $(
                     let mut [<_ $has_body>] = ();
 
-                    let body_range = self.body.take().as_mut().map(|body| write_body(bytes_ref, body));
+                    let body_range = self.body.take().as_mut().map(|body| $crate::__private::functions::write_body(bytes_ref, body));
                     )?
 
                     // End the frame
-                    write_frame_end(bytes_ref);
+                    $crate::__private::functions::write_frame_end(bytes_ref);
 
                     let ptr : *const [u8] = bytes.as_slice();
                     let slice = unsafe { ptr.as_ref().unwrap() };
@@ -143,20 +372,21 @@ macro_rules! sender_frame {
                     frame.raw = bytes;
 
                     $(
-                        if ![<$header_type Value>]::OWNED {
+                        // Spelt values are parsed from their text, so that they keep it
+                        if !<$crate::headers::[<$header_type Value>] as $crate::headers::HeaderValue>::OWNED || [<$header_name _spelt>] {
                             let value = unsafe { std::str::from_utf8_unchecked(&slice[[<$header_name _range>].0..[<$header_name _range>].1]) };
-                            frame.$header_name = [<$header_type Value>]::from_str(value).expect("Should never fail because string valued");
+                            frame.$header_name = $crate::headers::[<$header_type Value>]::from_str(value).expect("Should never fail because string valued or spelt as it was read");
                         }
                     )*
 
                     $($(
-                        if let Some((_,[<$opt_header_name _range>])) = [<$opt_header_name _range>] {
-                            if ![<$opt_header_type Value>]::OWNED {
+                        if let Some(((_,[<$opt_header_name _range>]), spelt)) = [<$opt_header_name _range>] {
+                            if !<$crate::headers::[<$opt_header_type Value>] as $crate::headers::HeaderValue>::OWNED || spelt {
                                 let value = unsafe { std::str::from_utf8_unchecked(&slice[[<$opt_header_name _range>].0..[<$opt_header_name _range>].1]) };
-                                choose_from_presence!($($opt_header_default)? {
-                                    frame.$opt_header_name = [<$opt_header_type Value>]::from_str(value).expect("Should never fail because string valued");
+                                $crate::__stomp_choose_from_presence!($($opt_header_default)? {
+                                    frame.$opt_header_name = $crate::headers::[<$opt_header_type Value>]::from_str(value).expect("Should never fail because string valued or spelt as it was read");
                                 }, {
-                                    frame.$opt_header_name = Some([<$opt_header_type Value>]::from_str(value).expect("Should never fail because string valued"));
+                                    frame.$opt_header_name = Some($crate::headers::[<$opt_header_type Value>]::from_str(value).expect("Should never fail because string valued or spelt as it was read"));
                                 });
                             }
                         };
@@ -167,7 +397,7 @@ macro_rules! sender_frame {
                             let name = unsafe { std::str::from_utf8_unchecked(&slice[ranges.0.0..ranges.0.1]) };
                             let value = unsafe { std::str::from_utf8_unchecked(&slice[ranges.1.0..ranges.1.1]) };
 
-                            CustomValue::new(name, value)
+                            $crate::headers::CustomValue::new(name, value)
                         }).collect();
                     )?
 
@@ -183,6 +413,103 @@ macro_rules! sender_frame {
                     frame
                 }
             }
+
+            #[doc = "Creates a builder pre-filled with the headers and body of the [`"$name"`], so that a modified copy"]
+            #[doc = "of the frame can be built."]
+            impl <'a> From<&$name<'a>> for [<$name Builder>] {
+                fn from(frame: &$name<'a>) -> Self {
+                    #[allow(unused_mut)]
+                    let mut builder = [<$name Builder>] {
+                        $(
+                            $header_name: Some(frame.$header_name.to_owned_value()),
+                        )*
+                        $($(
+                            $opt_header_name: $crate::__stomp_choose_from_presence!($($opt_header_default)? {
+                                Some(frame.$opt_header_name.to_owned_value())
+                            }, {
+                                frame.$opt_header_name.as_ref().map(|value| value.to_owned_value())
+                            }),
+                        )*)?
+                        $(
+                            custom: $crate::__stomp_choose_from_presence!($has_custom {
+                                frame.custom.iter().map(|header| {
+                                    let name = header.decoded_name().expect("Header names are validated when parsed or built");
+                                    let value = $crate::headers::DecodableValue::decoded_value(header).expect("Header values are validated when parsed or built");
+                                    (name.either(str::to_owned, |name| name), value.either(str::to_owned, |value| value))
+                                }).collect()
+                            }, {Vec::new()}),
+                        )?
+                        $(
+                            body: $crate::__stomp_choose_from_presence!($has_body {frame.body().map(<[u8]>::to_vec)}, {None}),
+                            $(
+                                // Keep the frame's choice of whether to send the length
+                                auto_content_length: frame.$length_header.is_some(),
+                            )?
+                        )?
+                        spellings: vec![
+                            $(
+                                ($crate::headers::[<$header_type Value>]::NAME, frame.$header_name.raw()),
+                            )*
+                            $($(
+                                ($crate::headers::[<$opt_header_type Value>]::NAME, $crate::__stomp_choose_from_presence!($($opt_header_default)? {
+                                    frame.$opt_header_name.raw()
+                                }, {
+                                    frame.$opt_header_name.as_ref().and_then(|value| value.raw())
+                                })),
+                            )*)?
+                        ].into_iter().filter_map(|(name, raw): (&'static str, Option<&str>)| raw.map(|raw| {
+                            let text = $crate::__private::functions::decode_str(raw).expect("Header values are validated when parsed or built");
+                            (name, text.either(str::to_owned, |text| text))
+                        })).collect(),
+                        state: std::marker::PhantomData,
+                    };
+                    $($(
+                        // The length is taken from the body, which it may not match under a lenient policy
+                        builder.$length_header = None;
+                    )?)?
+                    builder
+                }
+            }
+
+            // Only when this crate is built with the `arbitrary` feature, whichever crate invokes the macro
+            $crate::__stomp_if_arbitrary! {
+                #[doc = "Generates builders for valid [`"$name"`]s, for use in structure-aware fuzzing."]
+                impl<'a> $crate::__private::arbitrary::Arbitrary<'a> for [<$name Builder>] {
+                    fn arbitrary(u: &mut $crate::__private::arbitrary::Unstructured<'a>) -> $crate::__private::arbitrary::Result<Self> {
+                        #[allow(unused_mut)]
+                        let mut builder = [<$name Builder>] {
+                            $(
+                                $header_name: Some(u.arbitrary()?),
+                            )*
+                            $($(
+                                $opt_header_name: u.arbitrary()?,
+                            )*)?
+                            $(
+                                // Custom headers may neither be nameless nor masquerade as a known header
+                                custom: $crate::__stomp_choose_from_presence!($has_custom {
+                                    u.arbitrary::<Vec<(String, String)>>()?
+                                        .into_iter()
+                                        .filter(|(name, _)| !name.is_empty() && $crate::headers::HeaderType::from_name(name).is_none())
+                                        .collect()
+                                }, {Vec::new()}),
+                            )?
+                            $(
+                                body: $crate::__stomp_choose_from_presence!($has_body {u.arbitrary()?}, {None}),
+                                $(
+                                    auto_content_length: $crate::__stomp_true_if_present!($length_header),
+                                )?
+                            )?
+                            spellings: Vec::new(),
+                            state: std::marker::PhantomData,
+                        };
+                        $($(
+                            // An arbitrary explicit length would not match the body
+                            builder.$length_header = None;
+                        )?)?
+                        Ok(builder)
+                    }
+                }
+            }
         }
     }
 }
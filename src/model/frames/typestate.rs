@@ -0,0 +1,61 @@
+//! The states which the builders record, in their type parameters, for each of the required headers of a frame.
+//! A frame can only be built once all of its required headers are in a [`Provided`] state.
+//!
+//! ```
+//! use stomp_parser::client::SendFrame;
+//!
+//! let frame = SendFrame::builder()
+//!     .destination("stairway/to/heaven".to_owned())
+//!     .body(b"Lorem ipsum".to_vec())
+//!     .build();
+//!
+//! assert_eq!("stairway/to/heaven", frame.destination().value());
+//! ```
+//!
+//! Omitting a required header is a compile-time error:
+//!
+//! ```compile_fail
+//! use stomp_parser::client::SendFrame;
+//!
+//! let frame = SendFrame::builder().body(b"Lorem ipsum".to_vec()).build();
+//! ```
+
+/// The header has not been set yet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unset;
+
+/// The header has been set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Set;
+
+/// The `subscription` header of a `MESSAGE` has been set from a
+/// [`ClientAckSubscription`](crate::server::ClientAckSubscription), so that the `ack` header may be set too.
+/// The subscription of a builder whose `subscription` header is already set cannot be replaced by one:
+///
+/// ```compile_fail
+/// use stomp_parser::client::SubscribeFrame;
+/// use stomp_parser::headers::AckType;
+/// use stomp_parser::server::{ClientAckSubscription, MessageFrame};
+///
+/// let subscribe = SubscribeFrame::builder()
+///     .destination("a".to_owned())
+///     .id("s-1".to_owned())
+///     .ack_type(AckType::Client)
+///     .build();
+/// let subscription = ClientAckSubscription::from_subscribe(&subscribe).unwrap();
+///
+/// MessageFrame::builder()
+///     .message_id("m-1".to_owned())
+///     .destination("a".to_owned())
+///     .subscription("s-2".to_owned())
+///     .client_ack_subscription(&subscription);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientAck;
+
+/// The states in which a required header has a value.
+pub trait Provided {}
+
+impl Provided for Set {}
+
+impl Provided for ClientAck {}
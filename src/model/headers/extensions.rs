@@ -0,0 +1,221 @@
+//! Typed values for headers which are not part of the STOMP specification, but which are defined,
+//! and widely used, by brokers such as ActiveMQ, Artemis and RabbitMQ.
+//!
+//! Frames carry these headers in their `custom` list; the accessor traits [`MessageExtensions`],
+//! [`DeliveryExtensions`] and [`SubscriptionExtensions`] find and parse them, and the builder traits
+//! [`MessageBuilderExtensions`], [`DeliveryBuilderExtensions`] and [`SubscriptionBuilderExtensions`] set them.
+use super::*;
+use crate::client::{SendFrame, SendFrameBuilder, SubscribeFrame, SubscribeFrameBuilder};
+use crate::server::{MessageFrame, MessageFrameBuilder};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A point in time, as the number of milliseconds since the Unix epoch, in the form used by the
+/// `expires` header. By convention, `0` means "never".
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    /// The point in time this timestamp represents.
+    pub fn system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.0)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    /// Converts the time, truncated to milliseconds; times before the epoch become `0`.
+    fn from(time: SystemTime) -> Self {
+        Timestamp(
+            time.duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis() as u64),
+        )
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Timestamp {
+    type Err = StompParseError;
+    fn from_str(input: &str) -> Result<Timestamp, StompParseError> {
+        u64::from_str(input)
+            .map(Timestamp)
+            .map_err(|_| StompParseError::new(format!("Poorly formatted timestamp: {}", input)))
+    }
+}
+
+header!(CorrelationId, "correlation-id");
+header!(Expires, "expires", Timestamp, (Timestamp(0)));
+header!(MessageTtl, "x-message-ttl", u64, 0);
+header!(Persistent, "persistent", bool, false);
+header!(PrefetchCount, "prefetch-count", u32, 0);
+header!(Priority, "priority", u8, 4);
+header!(Redelivered, "redelivered", bool, false);
+header!(ReplyTo, "reply-to");
+header!(Selector, "selector");
+
+macro_rules! extensions {
+    ( @impls $trait:ident, $builder_trait:ident, [], $methods:tt ) => {};
+
+    ( @impls $trait:ident, $builder_trait:ident, [$frame:ident $(, $rest:ident)*], { $( $method:ident : $header:ident ),+ } ) => {
+        paste! {
+            impl<'f> $trait for $frame<'f> {
+                $(
+                    fn $method(&self) -> Result<Option<[<$header Value>]<'_>>, StompParseError> {
+                        self.custom_header()
+                    }
+                )+
+            }
+
+            impl $builder_trait for [<$frame Builder>] {
+                $(
+                    fn $method(self, value: <[<$header Value>]<'static> as HeaderValue>::OwnedValue) -> Self {
+                        self.custom_header::<[<$header Value>]>(value)
+                    }
+                )+
+            }
+        }
+
+        extensions!(@impls $trait, $builder_trait, [$($rest),*], { $( $method : $header ),+ });
+    };
+
+    ( $trait:ident, $builder_trait:ident, $comment:literal, [$($frame:ident),+], $( $method:ident : $header:ident ),+ ) => {
+        paste! {
+            #[doc = "Typed access to the extension headers " $comment "."]
+            pub trait $trait {
+                $(
+                    #[doc = "The value of the first `" $method "` header, if any."]
+                    fn $method(&self) -> Result<Option<[<$header Value>]<'_>>, StompParseError>;
+                )+
+            }
+
+            #[doc = "Sets the extension headers " $comment " on a builder."]
+            pub trait $builder_trait {
+                $(
+                    #[doc = "Sets the `" $method "` header to the value, replacing any set before."]
+                    fn $method(self, value: <[<$header Value>]<'static> as HeaderValue>::OwnedValue) -> Self;
+                )+
+            }
+
+        }
+
+        extensions!(@impls $trait, $builder_trait, [$($frame),+], { $( $method : $header ),+ });
+    };
+}
+
+extensions!(
+    MessageExtensions,
+    MessageBuilderExtensions,
+    "which brokers support on the messages sent and delivered",
+    [SendFrame, MessageFrame],
+    correlation_id: CorrelationId,
+    expires: Expires,
+    message_ttl: MessageTtl,
+    persistent: Persistent,
+    priority: Priority,
+    reply_to: ReplyTo
+);
+
+extensions!(
+    DeliveryExtensions,
+    DeliveryBuilderExtensions,
+    "which only brokers set, on the messages they deliver",
+    [MessageFrame],
+    redelivered: Redelivered
+);
+
+extensions!(
+    SubscriptionExtensions,
+    SubscriptionBuilderExtensions,
+    "which brokers support on subscriptions",
+    [SubscribeFrame],
+    message_ttl: MessageTtl,
+    prefetch_count: PrefetchCount,
+    selector: Selector
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::ClientFrame;
+    use std::convert::TryFrom;
+
+    fn send_frame(bytes: &[u8]) -> SendFrame<'static> {
+        match ClientFrame::try_from(bytes.to_vec()) {
+            Ok(ClientFrame::Send(frame)) => frame,
+            _ => panic!("Send Frame not parsed correctly"),
+        }
+    }
+
+    #[test]
+    fn parses_extension_headers() {
+        let frame = send_frame(
+            b"SEND\ndestination:foo\npersistent:true\npriority:9\nexpires:1500\n\
+            reply-to:/queue/a\\cb\ncorrelation-id:c-1\n\n\x00",
+        );
+
+        assert!(*frame.persistent().unwrap().unwrap().value());
+        assert_eq!(9, *frame.priority().unwrap().unwrap().value());
+        assert_eq!(
+            UNIX_EPOCH + Duration::from_millis(1500),
+            frame.expires().unwrap().unwrap().value().system_time()
+        );
+        assert_eq!(
+            "/queue/a:b",
+            &*frame.reply_to().unwrap().unwrap().decoded_value().unwrap()
+        );
+        assert_eq!("c-1", frame.correlation_id().unwrap().unwrap().value());
+    }
+
+    #[test]
+    fn first_extension_header_wins() {
+        let frame = send_frame(b"SEND\ndestination:foo\npriority:1\npriority:2\n\n\x00");
+
+        assert_eq!(1, *frame.priority().unwrap().unwrap().value());
+    }
+
+    #[test]
+    fn builder_replaces_extension_header() {
+        let frame = SendFrame::builder()
+            .destination("foo".to_owned())
+            .priority(1)
+            .priority(9)
+            .build();
+
+        assert_eq!(9, *frame.priority().unwrap().unwrap().value());
+        assert_eq!(1, frame.custom.len());
+    }
+
+    #[test]
+    fn rejects_malformed_extension_header() {
+        let frame = send_frame(b"SEND\ndestination:foo\npriority:high\n\n\x00");
+
+        assert!(frame.priority().is_err());
+    }
+
+    #[test]
+    fn builder_sets_extension_headers() {
+        let frame = SubscribeFrame::builder()
+            .destination("foo".to_owned())
+            .id("s-1".to_owned())
+            .selector("colour = 'red'".to_owned())
+            .prefetch_count(10)
+            .build();
+
+        assert_eq!("colour = 'red'", frame.selector().unwrap().unwrap().value());
+        assert_eq!(10, *frame.prefetch_count().unwrap().unwrap().value());
+
+        let frame = MessageFrame::builder()
+            .message_id("m-1".to_owned())
+            .destination("foo".to_owned())
+            .subscription("s-1".to_owned())
+            .redelivered(true)
+            .expires(Timestamp(1500))
+            .build();
+
+        assert!(*frame.redelivered().unwrap().unwrap().value());
+        assert_eq!(Timestamp(1500), *frame.expires().unwrap().unwrap().value());
+    }
+}
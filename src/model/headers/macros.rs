@@ -1,24 +1,67 @@
-macro_rules! header_display {
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __stomp_header_display {
     ( ) => {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-            write!(f, "{}:{}", self.header_name(), self.value)
+            write!(
+                f,
+                "{}:{}",
+                $crate::headers::HeaderValue::header_name(self),
+                self.value
+            )
+        }
+    };
+    ( $raw:ident ) => {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+            let name = $crate::headers::HeaderValue::header_name(self);
+            match self.$raw {
+                Some(raw) => write!(f, "{}:{}", name, raw),
+                None => write!(f, "{}:{}", name, self.value),
+            }
         }
     };
 }
+/// Declares the typed value of a header, `<Header>Value`, in the same way as the values of the headers defined by
+/// the specification are declared. Its arguments are the name of the type, the name of the header and, for headers
+/// whose values are not strings, the type of the value, which must implement `FromStr` and `Display`, and its
+/// default. Typed values keep the text they were parsed from, so that a frame which is rebuilt from them writes them as
+/// they were read, even if they are displayed differently; `09`, for example, is read as `9` but still written as `09`.
+///
+/// The value implements [`TypedHeaderValue`](crate::headers::TypedHeaderValue), so it can be read from, and added
+/// to, the custom headers of any frame which allows them, with `custom_header`.
+///
+/// ```
+/// use stomp_parser::client::SendFrame;
+///
+/// stomp_parser::header!(Priority, "priority", u8, 4);
+/// stomp_parser::header!(ReplyTo, "reply-to");
+///
+/// let frame = SendFrame::builder()
+///     .destination("a".to_owned())
+///     .custom_header::<PriorityValue>(9)
+///     .custom_header::<ReplyToValue>("/queue/b".to_owned())
+///     .build();
+///
+/// assert_eq!(9, *frame.custom_header::<PriorityValue>().unwrap().unwrap().value());
+/// assert_eq!("/queue/b", frame.custom_header::<ReplyToValue>().unwrap().unwrap().value());
+/// ```
+#[macro_export]
 macro_rules! header {
     ( $header:ident, $name:expr $(,$types:ty $(, $default:expr )?)? ) => {
-        paste! {
+        $crate::__private::paste::paste! {
 
-                #[derive(Eq, PartialEq, Clone)]
+                #[derive(Clone)]
                 pub struct [<$header Value>]<'a> {
-                    value: or_else_type!($($types)?,&'a str),
-                    phantom: std::marker::PhantomData<&'a or_else_type!($($types)?, str)>,
+                    value: $crate::__stomp_or_else_type!($($types)?,&'a str),
+                    raw: Option<&'a str>,
+                    phantom: std::marker::PhantomData<&'a $crate::__stomp_or_else_type!($($types)?, str)>,
                 }
 
                 impl <'a> Default for [<$header Value>]<'a> {
                     fn default() -> Self {
                         [<$header Value>] {
-                            value: or_else!($($($default)?)?,EMPTY),
+                            value: $crate::__stomp_or_else!($($($default)?)?,""),
+                            raw: None,
                             phantom: std::marker::PhantomData
                         }
                     }
@@ -28,62 +71,122 @@ macro_rules! header {
 
                     pub const NAME: &'static str =  $name;
 
-                    pub(crate) fn new(value: or_else_type!($($types)?,&'a str)) -> Self {
+                    pub(crate) fn new(value: $crate::__stomp_or_else_type!($($types)?,&'a str)) -> Self {
                         [<$header Value>] {
                             value,
+                            raw: None,
                             phantom: std::marker::PhantomData
                         }
                     }
 
-                    pub(crate) fn from_owned(_value: or_else_type!($($types)?,String)) -> Self {
-                        choose_from_presence!($($types)? {
+                    // Only used for the headers of frames, by their builders
+                    #[allow(dead_code)]
+                    #[doc(hidden)]
+                    pub fn from_owned(_value: $crate::__stomp_or_else_type!($($types)?,String)) -> Self {
+                        $crate::__stomp_choose_from_presence!($($types)? {
                             Self::new(_value)
                         }, {
                             panic!("Macro error, should never be called");
                         })
                     }
 
-                    pub(crate) fn from_str<'b>(input: &'b str) -> Result<[<$header Value>]<'b>, StompParseError> {
-                        choose_from_presence!($($types)? ($($types)?::from_str(input).map([<$header Value>]::new)
-                            .map_err(|_| StompParseError::new("[<Error Parsing $header Value>]"))), (Ok([<$header Value>]::new(
-                                input
-                            ))))
+                    /// Typed values are parsed from the decoded text, and keep the text, so that they can be written as they
+                    /// were read. String values are kept as they are, once their escapes have been checked.
+                    #[doc(hidden)]
+                    #[allow(clippy::should_implement_trait)]
+                    pub fn from_str<'b>(input: &'b str) -> Result<[<$header Value>]<'b>, $crate::error::StompParseError> {
+                        $crate::__stomp_choose_from_presence!($($types)? ($crate::__private::functions::decode_str(input).and_then(|decoded| {
+                            <$($types)? as std::str::FromStr>::from_str(&decoded)
+                                .map(|value| [<$header Value>] { value, raw: Some(input), phantom: std::marker::PhantomData })
+                                .map_err(|_| $crate::error::StompParseError::new(format!("Poorly formatted {}: {}", $name, input)))
+                        })), ($crate::__private::functions::check_escapes(input).map([<$header Value>]::new)))
+                    }
+
+                    /// The value, pointing into `to` at the position at which it is in `from`.
+                    ///
+                    /// # Safety
+                    ///
+                    /// `to` must be byte-identical to `from`; see `rebase_str`.
+                    #[doc(hidden)]
+                    pub unsafe fn rebased(&self, _from: &[u8], _to: &'a [u8]) -> Self {
+                        [<$header Value>] {
+                            value: $crate::__stomp_choose_from_presence!($($types)? {
+                                self.value.clone()
+                            }, {
+                                $crate::__private::functions::rebase_str(self.value, _from, _to)
+                            }),
+                            raw: self.raw.map(|raw| $crate::__private::functions::rebase_str(raw, _from, _to)),
+                            phantom: std::marker::PhantomData
+                        }
+                    }
+
+                    /// The escaped text a typed value was parsed from, which a builder created from its frame writes it as.
+                    #[doc(hidden)]
+                    pub fn raw(&self) -> Option<&'a str> {
+                        self.raw
                     }
 
-                    pub fn value(&self) -> & or_else_type!($($types)?,str) {
-                        choose_from_presence!($($types)? {&self.value}, {&self.value})
+                    pub fn value(&self) -> & $crate::__stomp_or_else_type!($($types)?,str) {
+                        $crate::__stomp_choose_from_presence!($($types)? {&self.value}, {&self.value})
+                    }
+
+                    /// The value in the form a builder accepts it; string values are decoded.
+                    pub fn to_owned_value(&self) -> $crate::__stomp_or_else_type!($($types)?,String) {
+                        $crate::__stomp_choose_from_presence!($($types)? {
+                            self.value.clone()
+                        }, {
+                            // String values are only created by from_str, which checks their escapes, so they decode
+                            $crate::__private::functions::decode_str(self.value).expect("Escapes are checked when values are parsed").either(str::to_owned, |decoded| decoded)
+                        })
                     }
                 }
 
-                if_not_present!($($types)? (impl <'a> DecodableValue for [<$header Value>]<'a> {
-                        fn decoded_value(&self) -> Result<Either<&str, String>, StompParseError> {
-                            decode_str(self.value())
+                $crate::__stomp_if_not_present!($($types)? (impl <'a> $crate::headers::DecodableValue for [<$header Value>]<'a> {
+                        fn decoded_value(&self) -> Result<$crate::__private::Either<&str, String>, $crate::error::StompParseError> {
+                            $crate::__private::functions::decode_str(self.value())
                         }
                     }
                 ));
 
-                impl <'a> HeaderValue  for [<$header Value>]<'a> {
-                    type OwnedValue = or_else_type!($($types)?,String);
-                    type Value=or_else_type!($($types)?,&'static str);
-                    const OWNED: bool = choose_from_presence!($($types)? true, false);
+                impl <'a> $crate::headers::HeaderValue for [<$header Value>]<'a> {
+                    type OwnedValue = $crate::__stomp_or_else_type!($($types)?,String);
+                    type Value=$crate::__stomp_or_else_type!($($types)?,&'static str);
+                    const OWNED: bool = $crate::__stomp_choose_from_presence!($($types)? true, false);
 
                     fn header_name(&self) -> &str {
                         [<$header Value>]::NAME
                     }
                 }
 
-                impl  <'a> Into<or_else_type!($($types)?,&'a str)> for [<$header Value>]<'a> {
-                    fn into(self) -> or_else_type!($($types)?,&'a str) {
-                        self.value
+                impl <'a> $crate::headers::TypedHeaderValue<'a> for [<$header Value>]<'a> {
+                    const NAME: &'static str = $name;
+
+                    fn parse(value: &'a str) -> Result<Self, $crate::error::StompParseError> {
+                        [<$header Value>]::from_str(value)
+                    }
+                }
+
+                impl <'a> From<[<$header Value>]<'a>> for $crate::__stomp_or_else_type!($($types)?,&'a str) {
+                    fn from(header: [<$header Value>]<'a>) -> $crate::__stomp_or_else_type!($($types)?,&'a str) {
+                        header.value
+                    }
+                }
+
+                // Values are equal whatever the text they were parsed from
+                impl <'a> PartialEq for [<$header Value>]<'a> {
+                    fn eq(&self, other: &Self) -> bool {
+                        self.value == other.value
                     }
                 }
 
+                impl <'a> Eq for [<$header Value>]<'a> {}
+
                 impl <'a> std::fmt::Display for [<$header Value>]<'a> {
-                    header_display!( );
+                    $crate::__stomp_header_display!(raw);
                 }
 
                 impl <'a> std::fmt::Debug for [<$header Value>]<'a> {
-                    header_display!( );
+                    $crate::__stomp_header_display!(raw);
                 }
 
         }
@@ -106,6 +209,19 @@ macro_rules! headers {
                     }
                 }
 
+                /// The header, pointing into `to` at the position at which it is in `from`.
+                ///
+                /// # Safety
+                ///
+                /// `to` must be byte-identical to `from`; see `rebase_str`.
+                #[doc(hidden)]
+                pub unsafe fn rebased(&self, from: &[u8], to: &'static [u8]) -> Self {
+                    CustomValue {
+                        name: rebase_str(self.name, from, to),
+                        value: rebase_str(self.value, from, to),
+                    }
+                }
+
                 pub fn value(&self) -> &&'static str {
                     &self.value
                 }
@@ -132,7 +248,7 @@ macro_rules! headers {
             }
 
              impl  std::fmt::Display for CustomValue {
-                header_display!( );
+                __stomp_header_display!( );
             }
 
 
@@ -155,16 +271,24 @@ macro_rules! headers {
             }
         }
 
-        impl TryFrom<&'static str> for HeaderType {
+        impl HeaderType {
+            /// The type of the header with the provided name, if it is one of the headers defined by the specification.
+            pub fn from_name(name: &str) -> Option<HeaderType> {
+                match name {
+                    $(
+                        $name => Some(HeaderType::$header),
+                    )*
+                    _ => None
+                }
+            }
+        }
+
+        /// Fails for the names of headers which the specification does not define.
+        impl TryFrom<&str> for HeaderType {
             type Error = StompParseError;
-            fn try_from(input: &'static str) -> std::result::Result<HeaderType, StompParseError> {
-                match(input) {
-                        $(
-                            $name => Ok(HeaderType::$header),
-                        )*
-                        _ => panic!("Not a known Header")
-//                        name => Ok(HeaderType::Custom(name))
-                    }
+            fn try_from(input: &str) -> std::result::Result<HeaderType, StompParseError> {
+                HeaderType::from_name(input)
+                    .ok_or_else(|| StompParseError::new(format!("Unknown header '{}'", input)))
             }
         }
 
@@ -192,27 +316,39 @@ macro_rules! headers {
                     Custom(CustomValue)
                 }
 
-                #[doc(hidden)]
-                pub mod parser {
-                    #![allow(non_snake_case)]
-
+                #[cfg(test)]
+                mod round_trip {
                     use super::*;
-                    pub type HeaderValueConverter = dyn Fn(&str) -> Result<Header, StompParseError>;
-
-                    pub fn find_header_parser(header_type: HeaderType) -> Box<HeaderValueConverter> {
-                        match header_type {
-                            $(
-                                HeaderType::$header => Box::new([<parse_ $header _header>]),
-                            )*
-                        }
-                    }
+                    use super::test::Samples;
+                    use crate::common::functions::{write_encoded, Spelling};
 
                     $(
-                        pub fn [<parse_ $header _header>](input: &str) -> Result<Header<'_>, StompParseError> {
-                            [<$header Value>]::from_str(input).map(Header::$header)
+                        #[test]
+                        fn [<$header:snake _values_round_trip>]() {
+                            for sample in <$crate::__stomp_or_else_type!($($types)?, &str) as Samples>::ROUND_TRIPPED {
+                                let value = [<$header Value>]::from_str(sample).expect("Sample should parse");
+                                let owned = value.to_owned_value();
+
+                                // As a builder created from the frame of the value writes it
+                                let spellings: Vec<_> = value.raw().map(|raw| ($name, decode_str(raw).unwrap().either(str::to_owned, |text| text))).into_iter().collect();
+                                let mut encoded = Vec::new();
+                                write_encoded(&mut encoded, &Spelling::new(&spellings, $name, &owned));
+
+                                assert_eq!(sample.as_bytes(), &encoded[..], "{} was not written as it was read", sample);
+                                assert_eq!(format!("{}:{}", $name, sample), value.to_string());
+
+                                // Without its spelling, the value is still written as something which reads as it
+                                let mut encoded = Vec::new();
+                                write_encoded(&mut encoded, &owned);
+
+                                assert_eq!(value, [<$header Value>]::from_str(std::str::from_utf8(&encoded).unwrap()).unwrap());
+                            }
+
+                            for sample in <$crate::__stomp_or_else_type!($($types)?, &str) as Samples>::REJECTED {
+                                assert!([<$header Value>]::from_str(sample).is_err(), "{} should be rejected", sample);
+                            }
                         }
                     )*
-
                 }
         }
     }
@@ -2,12 +2,15 @@
 //! [STOMP Protocol Specification,Version 1.2](https://stomp.github.io/stomp-specification-1.2.html).
 #[macro_use]
 mod macros;
-use crate::common::functions::decode_str;
+#[cfg(feature = "extensions")]
+pub mod extensions;
+use crate::common::functions::{decode_str, rebase_str};
 use crate::error::StompParseError;
 use either::Either;
 use paste::paste;
 use std::convert::TryFrom;
 use std::str::FromStr;
+use std::time::Duration;
 
 /// A Header that reveals it's type and it's value, and can be displayed
 pub trait HeaderValue: std::fmt::Display {
@@ -18,6 +21,17 @@ pub trait HeaderValue: std::fmt::Display {
     fn header_name(&self) -> &str;
 }
 
+/// A header with a typed value, which is parsed from the raw value of the headers with its name. The values declared
+/// with [`header!`](crate::header) implement it, so that they can be read from, and added to, the custom headers of
+/// frames with `custom_header`. If the header is absent, [`Default`] supplies its default value.
+pub trait TypedHeaderValue<'a>: HeaderValue + Default + Sized {
+    /// The name of the header.
+    const NAME: &'static str;
+
+    /// Parses the raw value of a header.
+    fn parse(value: &'a str) -> Result<Self, StompParseError>;
+}
+
 pub trait DecodableValue {
     fn decoded_value(&self) -> Result<Either<&str, String>, StompParseError>;
 }
@@ -54,6 +68,7 @@ impl FromStr for NameValue {
 /// A pair of numbers which specify at what intervall the originator of
 /// the containing message will supply a heartbeat and expect a heartbeat.
 #[derive(Eq, PartialEq, Debug, Clone, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct HeartBeatIntervals {
     pub supplied: u32,
     pub expected: u32,
@@ -90,9 +105,75 @@ impl FromStr for HeartBeatIntervals {
     }
 }
 
+/// The heart-beats of a connection, as negotiated from the `heart-beat` headers of its `CONNECT` and `CONNECTED` frames;
+/// see [`HeartBeatIntervals::negotiate`]. `None` means that heart-beats are disabled in that direction.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub struct NegotiatedHeartbeat {
+    /// How often this side must send a heart-beat, or any other data.
+    pub send_every: Option<Duration>,
+    /// How long this side waits for a heart-beat, or any other data, before it considers the connection dead. This
+    /// includes the grace allowed for network latency.
+    pub expect_within: Option<Duration>,
+}
+
+impl NegotiatedHeartbeat {
+    /// The grace multiplier which is commonly used, allowing heart-beats to be half a period late.
+    pub const DEFAULT_GRACE: f64 = 1.5;
+}
+
+impl HeartBeatIntervals {
+    /// Negotiates the heart-beats of this side, whose `heart-beat` header is `self`, with the other side, whose header
+    /// is `other`. In each direction, heart-beats are disabled if either side specifies 0, and otherwise are sent at
+    /// the larger of the two intervals. The interval at which heart-beats are expected is multiplied by `grace`, to
+    /// allow for network latency; see [`NegotiatedHeartbeat::DEFAULT_GRACE`].
+    ///
+    /// # Panics
+    ///
+    /// If `grace` is negative or not finite.
+    pub fn negotiate(&self, other: &HeartBeatIntervals, grace: f64) -> NegotiatedHeartbeat {
+        fn period(own: u32, other: u32) -> Option<Duration> {
+            if own == 0 || other == 0 {
+                None
+            } else {
+                Some(Duration::from_millis(u64::from(own.max(other))))
+            }
+        }
+
+        NegotiatedHeartbeat {
+            send_every: period(self.supplied, other.expected),
+            expect_within: period(self.expected, other.supplied)
+                .map(|period| period.mul_f64(grace)),
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct StompVersions(pub Vec<StompVersion>);
 
+impl StompVersions {
+    /// Negotiates the version of a connection: the highest of these versions, which the client accepts, that is also
+    /// `supported` by the server, if there is one. A client which accepts no versions is taken to accept 1.0, as the
+    /// specification requires. `Unknown` versions are never chosen, because they cannot be ordered.
+    ///
+    /// If there is no such version, the server should reply with
+    /// [`ErrorFrame::unsupported_versions`](crate::server::ErrorFrame::unsupported_versions).
+    pub fn negotiate(&self, supported: &StompVersions) -> Option<StompVersion> {
+        let accepted: &[StompVersion] = if self.0.is_empty() {
+            &[StompVersion::V1_0]
+        } else {
+            &self.0
+        };
+
+        accepted
+            .iter()
+            .filter(|version| supported.contains(version))
+            .filter_map(|version| version.rank().map(|rank| (rank, version)))
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, version)| version.clone())
+    }
+}
+
 impl std::fmt::Display for StompVersions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
         write!(
@@ -109,10 +190,15 @@ impl std::fmt::Display for StompVersions {
 
 impl FromStr for StompVersions {
     type Err = StompParseError;
+    /// Parses the comma-separated versions; an empty string has none.
     fn from_str(input: &str) -> Result<StompVersions, StompParseError> {
+        if input.is_empty() {
+            return Ok(StompVersions(Vec::new()));
+        }
+
         input
             .split(',')
-            .map(|section| StompVersion::from_str(section))
+            .map(StompVersion::from_str)
             .try_fold(Vec::new(), |mut vec, result| {
                 result
                     .map(|version| {
@@ -135,10 +221,12 @@ impl std::ops::Deref for StompVersions {
     }
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, PartialEq, Debug, Clone, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 /// The Ack approach to be used for the subscription
 pub enum AckType {
     /// The client need not send Acks. Messages are assumed received as soon as sent.
+    #[default]
     Auto,
     /// Client must send Ack frames. Ack frames are cummulative, acknowledging also all previous messages.
     Client,
@@ -146,12 +234,6 @@ pub enum AckType {
     ClientIndividual,
 }
 
-impl Default for AckType {
-    fn default() -> Self {
-        AckType::Auto
-    }
-}
-
 impl std::fmt::Display for AckType {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         f.write_str(match self {
@@ -181,18 +263,49 @@ pub enum StompVersion {
     V1_0,
     V1_1,
     V1_2,
+    /// A version this crate does not know, as it was spelt. It cannot contain a comma when part of
+    /// [`StompVersions`], which are separated by commas.
     Unknown(String),
 }
 
+impl StompVersion {
+    /// The order of the known versions, from oldest to newest.
+    fn rank(&self) -> Option<u8> {
+        match self {
+            StompVersion::V1_0 => Some(0),
+            StompVersion::V1_1 => Some(1),
+            StompVersion::V1_2 => Some(2),
+            StompVersion::Unknown(_) => None,
+        }
+    }
+}
+
 impl std::fmt::Display for StompVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        let text = match self {
+        f.write_str(match self {
             StompVersion::V1_0 => "1.0",
             StompVersion::V1_1 => "1.1",
             StompVersion::V1_2 => "1.2",
-            _ => return Err(std::fmt::Error {}),
-        };
-        f.write_str(text)
+            StompVersion::Unknown(version) => version,
+        })
+    }
+}
+
+/// `Unknown` versions are never empty, never contain a comma and never have the spelling of a known version, so that
+/// they are parsed as they were generated, alone or in [`StompVersions`].
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for StompVersion {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let unknown = String::arbitrary(u)?.replace(',', "");
+
+        Ok(match StompVersion::from_str(&unknown) {
+            Ok(StompVersion::Unknown(version)) if !version.is_empty() => {
+                StompVersion::Unknown(version)
+            }
+            _ => u
+                .choose(&[StompVersion::V1_0, StompVersion::V1_1, StompVersion::V1_2])?
+                .clone(),
+        })
     }
 }
 
@@ -208,8 +321,6 @@ impl FromStr for StompVersion {
     }
 }
 
-const EMPTY: &str = "";
-
 headers!(
     (Ack, "ack", AckType, (AckType::Auto)),
     (
@@ -251,7 +362,51 @@ mod test {
 
     use std::{fmt::Display, str::FromStr};
 
-    use super::{ContentLengthValue, DecodableValue, DestinationValue, HeaderValue};
+    use super::{
+        AckType, ContentLengthValue, DecodableValue, DestinationValue, HeaderValue,
+        NegotiatedHeartbeat, StompVersion, StompVersions,
+    };
+    use std::time::Duration;
+
+    /// The raw values with which the round trip of the headers of each type is tested, by the tests which `headers!`
+    /// generates for every header.
+    pub(super) trait Samples {
+        /// Values which are written exactly as they were read.
+        const ROUND_TRIPPED: &'static [&'static str];
+        /// Values which do not parse.
+        const REJECTED: &'static [&'static str];
+    }
+
+    impl Samples for &str {
+        const ROUND_TRIPPED: &'static [&'static str] = &["", "a", r"/queue/a\\b\cc\nd\re"];
+        const REJECTED: &'static [&'static str] = &[r"a\tb", r"ab\"];
+    }
+
+    impl Samples for u32 {
+        const ROUND_TRIPPED: &'static [&'static str] = &["0", "10", "4294967295", "007", "+1"];
+        const REJECTED: &'static [&'static str] = &["", " 1", "4294967296"];
+    }
+
+    impl Samples for AckType {
+        const ROUND_TRIPPED: &'static [&'static str] = &["auto", "client", "client-individual"];
+        const REJECTED: &'static [&'static str] = &["", "Auto", "client "];
+    }
+
+    impl Samples for HeartBeatIntervals {
+        const ROUND_TRIPPED: &'static [&'static str] = &["0,0", "100,20000", "01,2", "+1,2"];
+        const REJECTED: &'static [&'static str] = &["", "1", "1, 2", "1,2,3"];
+    }
+
+    impl Samples for StompVersion {
+        const ROUND_TRIPPED: &'static [&'static str] = &["1.0", "1.1", "1.2", "1.3", r"v1\c2"];
+        const REJECTED: &'static [&'static str] = &[r"1.2\t"];
+    }
+
+    impl Samples for StompVersions {
+        const ROUND_TRIPPED: &'static [&'static str] =
+            &["", "1.2", "1.0,1.1,1.2", "1.1,1.2,funk", "1.2, 1.1", "1.1,"];
+        const REJECTED: &'static [&'static str] = &[r"1.2\t"];
+    }
 
     fn do_something(value: &str) {
         println!("Value: {}", value);
@@ -264,10 +419,6 @@ mod test {
         let value: &str = d.value();
 
         do_something(value);
-
-        drop(d);
-
-        //        println!("Value: {}", value);
     }
 
     #[test]
@@ -411,9 +562,7 @@ mod test {
 
         let result = instance.decoded_value();
 
-        if let Ok(_) = result {
-            panic!("Unexpected return");
-        }
+        assert!(result.is_err(), "Unexpected return");
     }
 
     #[test]
@@ -423,8 +572,78 @@ mod test {
 
         let result = instance.decoded_value();
 
-        if let Ok(_) = result {
-            panic!("Unexpected return");
-        }
+        assert!(result.is_err(), "Unexpected return");
+    }
+
+    #[test]
+    fn negotiates_highest_mutual_version() {
+        let supported = StompVersions::from_str("1.0,1.1,2.0").unwrap();
+
+        let negotiate = |accepted: &str| {
+            StompVersions::from_str(accepted)
+                .unwrap()
+                .negotiate(&supported)
+        };
+
+        assert_eq!(Some(StompVersion::V1_1), negotiate("1.2,1.0,1.1"));
+        assert_eq!(Some(StompVersion::V1_0), negotiate(""));
+        assert_eq!(None, negotiate("1.2,2.0"));
+    }
+
+    #[test]
+    fn negotiates_heartbeats_in_each_direction() {
+        let client = HeartBeatIntervals::new(1000, 5000);
+        let server = HeartBeatIntervals::new(4000, 0);
+
+        assert_eq!(
+            NegotiatedHeartbeat {
+                send_every: None,
+                expect_within: Some(Duration::from_millis(7500))
+            },
+            client.negotiate(&server, NegotiatedHeartbeat::DEFAULT_GRACE)
+        );
+        assert_eq!(
+            NegotiatedHeartbeat {
+                send_every: Some(Duration::from_millis(5000)),
+                expect_within: None
+            },
+            server.negotiate(&client, 2.0)
+        );
+        assert_eq!(
+            NegotiatedHeartbeat::default(),
+            HeartBeatIntervals::default().negotiate(&client, 1.0)
+        );
+    }
+
+    #[test]
+    fn unknown_versions_keep_their_spelling() {
+        let versions = StompVersions::from_str("1.1,1.2,funk, 1.0").unwrap();
+
+        assert_eq!(
+            vec![
+                StompVersion::V1_1,
+                StompVersion::V1_2,
+                StompVersion::Unknown("funk".to_owned()),
+                StompVersion::Unknown(" 1.0".to_owned())
+            ],
+            versions.0
+        );
+        assert_eq!("1.1,1.2,funk, 1.0", versions.to_string());
+        assert_eq!(
+            StompVersions(Vec::new()),
+            StompVersions::from_str("").unwrap()
+        );
+    }
+
+    #[test]
+    fn header_type_try_from_fails_for_unknown_names() {
+        use super::HeaderType;
+        use std::convert::TryFrom;
+
+        assert_eq!(
+            HeaderType::ContentType,
+            HeaderType::try_from("content-type").unwrap()
+        );
+        assert!(HeaderType::try_from("x-unknown").is_err());
     }
 }
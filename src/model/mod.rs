@@ -1,8 +1,14 @@
+pub mod body;
+pub mod destination;
 mod frames;
 pub mod headers;
 
+pub use frames::any;
 pub use frames::client;
+pub use frames::frame;
+pub use frames::raw;
 pub use frames::server;
+pub use frames::typestate;
 
 #[cfg(test)]
 mod test {
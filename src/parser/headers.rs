@@ -1,225 +1,183 @@
-use core::ops::FnMut;
-
-use nom::bytes::complete::{escaped, is_not};
-use nom::character::complete::{char, line_ending, one_of};
-use nom::combinator::{flat_map, map_res};
-use nom::error::context;
-use nom::multi::many0;
-use nom::sequence::terminated;
-use nom::IResult;
-use nom::Parser;
-
-use crate::error::{FullError, StompParseError};
-use crate::model::headers::parser::*;
-use crate::model::headers::*;
-
-trait HeaderParser<'a, E: FullError<&'a [u8], StompParseError>>:
-    FnMut(&'a [u8]) -> IResult<&'a [u8], Header, E> + 'a
-{
+use crate::common::functions::write_encoded_header;
+use crate::error::StompParseError;
+use crate::parser::command_line;
+
+/// The result of lexing a single line of the header section.
+enum Line<'a> {
+    /// A header, with its (still encoded) name and value, followed by the remaining input.
+    Header(&'a str, &'a str, &'a [u8]),
+    /// The blank line which terminates the headers, followed by the remaining input.
+    End(&'a [u8]),
 }
 
-impl<'a, E, T> HeaderParser<'a, E> for T
-where
-    E: FullError<&'a [u8], StompParseError>,
-    T: FnMut(&'a [u8]) -> IResult<&'a [u8], Header, E> + 'a,
-{
+/// Iterates over the header lines at the start of the input, yielding the raw name and value
+/// of each, in the order in which they occur. Iteration stops after the blank line which
+/// terminates the headers, or at the first malformed line.
+///
+/// Lexing is done in a single pass over the bytes, without allocating.
+pub struct HeaderLines<'a> {
+    remaining: &'a [u8],
+    finished: bool,
 }
 
-/// Creates an new HeadersParser accepting the specified required and optional Headers,
-/// and optionally arbitrary other headers as "custom" headers.
-pub fn headers_parser<'a, E>(
-    required: Vec<HeaderType>,
-    optional: Vec<HeaderType>,
-    allows_custom: bool,
-) -> Box<dyn Parser<&'a [u8], Vec<Header<'a>>, E> + 'a>
-where
-    E: 'a + FullError<&'a [u8], StompParseError>,
-{
-    let parser_selector = init_headers_parser(required, optional, allows_custom);
-
-    Box::new(terminated(
-        many0(flat_map(header_name, parser_selector)), // Accept many headers...
-        context("header_terminator", line_ending),     //...terminated by a blank line
-    ))
-}
+impl<'a> HeaderLines<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        HeaderLines {
+            remaining: input,
+            finished: false,
+        }
+    }
 
-fn init_headers_parser<'a, E>(
-    required: Vec<HeaderType>,
-    optional: Vec<HeaderType>,
-    allows_custom: bool,
-) -> Box<dyn Fn(&'a str) -> Box<dyn HeaderParser<'a, E>> + 'a>
-where
-    E: 'a + FullError<&'a [u8], StompParseError>,
-{
-    // The part that deals with the specified required and optional headers
-    let known_headers = init_known_header_parser(required, optional, allows_custom);
-
-    // The part that deals with any other headers encountered
-    //let custom_header_parser_provider = custom_header_parser_provider_factory(allows_custom);
-    Box::new(move |name: &'a str| {
-        // Determine the type
-        known_headers(name) // Then see if it is a known header, and return the appropriate parser
-            .unwrap_or_else(|_| disallowed_header_parser(name))
-    })
+    /// The input following the headers. Only meaningful once the iterator has been exhausted
+    /// without returning an error.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.remaining
+    }
 }
 
-fn find_header<'a, 'b, E>(
-    name: &'a str,
-    required: &'b Vec<HeaderType>,
-    optional: &'b Vec<HeaderType>,
-    allows_custom: bool,
-) -> Result<Box<dyn HeaderParser<'a, E> + 'a>, StompParseError>
-where
-    'a: 'b,
-    E: 'a + FullError<&'a [u8], StompParseError>,
-{
-    required
-        .iter()
-        .find(|header_type| header_type.matches(name))
-        .or_else(|| {
-            optional
-                .iter()
-                .find(|header_type| header_type.matches(name))
-        })
-        .map(|header_type| {
-            Ok(known_header_parser::<'a, E>(find_header_parser(
-                *header_type,
-            )))
-        })
-        .unwrap_or_else(|| {
-            if allows_custom {
-                Ok(known_header_parser::<'a, E>(Box::new(
-                    move |value: &str| {
-                        Ok(Header::Custom(CustomValue::new(
-                            unsafe { std::mem::transmute::<&'a str, &'static str>(name) },
-                            unsafe { std::mem::transmute::<&'_ str, &'static str>(value) },
-                        )))
-                    },
-                )))
-            } else {
-                Err(StompParseError::new(format!("Unknown header: {}", name)))
+impl<'a> Iterator for HeaderLines<'a> {
+    type Item = Result<(&'a str, &'a str), StompParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        match header_line(self.remaining) {
+            Ok(Line::Header(name, value, remaining)) => {
+                self.remaining = remaining;
+                Some(Ok((name, value)))
+            }
+            Ok(Line::End(remaining)) => {
+                self.remaining = remaining;
+                self.finished = true;
+                None
+            }
+            Err(error) => {
+                self.finished = true;
+                Some(Err(error))
             }
-        })
+        }
+    }
 }
 
-fn init_known_header_parser<'a, E>(
-    required: Vec<HeaderType>,
-    optional: Vec<HeaderType>,
-    allows_custom: bool,
-) -> impl Fn(&'a str) -> Result<Box<dyn HeaderParser<'a, E>>, StompParseError> + 'a
-where
-    E: 'a + FullError<&'a [u8], StompParseError>,
-{
-    move |name: &'a str| find_header(name, &required, &optional, allows_custom)
+fn header_line(input: &[u8]) -> Result<Line<'_>, StompParseError> {
+    match input {
+        [b'\n', remaining @ ..] | [b'\r', b'\n', remaining @ ..] => {
+            return Ok(Line::End(remaining))
+        }
+        _ => {}
+    }
+
+    let mut separator: Option<usize> = None;
+    let mut index = 0;
+
+    while index < input.len() {
+        match input[index] {
+            b'\\' => match input.get(index + 1) {
+                Some(b'r' | b'n' | b'c' | b'\\') => index += 1,
+                _ => return Err(StompParseError::new("Invalid escape sequence in header")),
+            },
+            b':' if separator.is_none() => separator = Some(index),
+            b':' => return Err(StompParseError::new("Unescaped ':' in header value")),
+            b'\n' => return header(input, separator, index, index + 1),
+            b'\r' if input.get(index + 1) == Some(&b'\n') => {
+                return header(input, separator, index, index + 2)
+            }
+            b'\r' => return Err(StompParseError::new("Unescaped carriage return in header")),
+            _ => {}
+        }
+        index += 1;
+    }
+
+    Err(StompParseError::new("Header line not terminated"))
 }
 
-fn header_section<'a, E: FullError<&'a [u8], StompParseError>>(
-    input: &'a [u8],
-) -> IResult<&'a [u8], &'a [u8], E> {
-    escaped(is_not("\\:\n\r"), '\\', one_of("rnc\\"))(input)
+fn header(
+    input: &[u8],
+    separator: Option<usize>,
+    value_end: usize,
+    line_end: usize,
+) -> Result<Line<'_>, StompParseError> {
+    match separator {
+        // Unlike values, names may not be empty
+        Some(separator) if separator > 0 => Ok(Line::Header(
+            into_string(&input[..separator])?,
+            into_string(&input[separator + 1..value_end])?,
+            &input[line_end..],
+        )),
+        Some(_) => Err(StompParseError::new("Header name is empty")),
+        None => Err(StompParseError::new("Header line without ':'")),
+    }
 }
 
-fn into_string<'a>(input: &'a [u8]) -> Result<&'a str, StompParseError> {
+fn into_string(input: &[u8]) -> Result<&str, StompParseError> {
     std::str::from_utf8(input).map_err(|_| StompParseError::new("bytes are not utf8"))
 }
 
-fn header_name<'a, E: FullError<&'a [u8], StompParseError>>(
-    input: &'a [u8],
-) -> IResult<&'a [u8], &'a str, E> {
-    context(
-        "header name",
-        map_res(terminated(header_section, char(':')), into_string),
-    )(input)
-}
+/// Sets the header with the given name in the raw frame to the given value, escaping both as
+/// required. The first line carrying the header, which is the one that applies if the header is
+/// repeated, is replaced in place, keeping its line terminator; if there is none, the header is
+/// inserted directly after the command line, with the same terminator as it. All other bytes are
+/// left untouched.
+pub fn splice_header(raw: &mut Vec<u8>, name: &str, value: &str) -> Result<(), StompParseError> {
+    let mut line = Vec::new();
+    let (name_range, _) = write_encoded_header(&mut line, name, value);
+    let encoded_name = &line[name_range.0..name_range.1];
+
+    let (headers, _) = command_line::<nom::error::VerboseError<&[u8]>, StompParseError>(raw)
+        .map_err(|_| StompParseError::new("Error parsing frame"))?;
+    let headers_start = raw.len() - headers.len();
+
+    let mut lines = HeaderLines::new(headers);
+    let mut target = headers_start..headers_start;
+
+    while let Some(header) = lines.next() {
+        let (current_name, _) = header?;
+
+        if current_name.as_bytes() == encoded_name {
+            let start = current_name.as_ptr() as usize - raw.as_ptr() as usize;
+            target = start..raw.len() - lines.remaining().len();
+            break;
+        }
+    }
 
-fn header_value<'a, E: FullError<&'a [u8], StompParseError>>(
-    input: &'a [u8],
-) -> IResult<&'a [u8], &'a str, E> {
-    context(
-        "header value",
-        map_res(terminated(header_section, line_ending), into_string),
-    )(input)
-}
+    if raw[..target.end].ends_with(b"\r\n") {
+        line.insert(line.len() - 1, b'\r');
+    }
 
-fn disallowed_header_parser<'a, E: 'a + FullError<&'a [u8], StompParseError>>(
-    name: &'a str,
-) -> Box<dyn HeaderParser<'a, E>> {
-    Box::new(map_res(header_value, move |_| {
-        Err(StompParseError::new(format!(
-            "Unexpected header '{}' encountered",
-            name
-        )))
-    }))
-}
+    raw.splice(target, line);
 
-fn known_header_parser<'a, E: 'a + FullError<&'a [u8], StompParseError>>(
-    parser: Box<dyn Fn(&str) -> Result<Header, StompParseError> + 'a>,
-) -> Box<dyn HeaderParser<'a, E>> {
-    Box::new(map_res(header_value, parser))
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use either::Either;
-    use nom::error::dbg_dmp;
-    use nom::error::VerboseError;
+    use std::convert::TryFrom;
 
-    use super::headers_parser;
-    use crate::error::{FullError, StompParseError};
+    use crate::client::{ClientFrame, ConnectFrame, SendFrame};
+    use crate::error::StompParseError;
     use crate::model::headers::*;
-    use nom::IResult;
-    use std::vec::Vec;
-
-    fn header<E: 'static + FullError<&'static [u8], StompParseError> + std::fmt::Debug>(
-        input: &'static [u8],
-    ) -> IResult<&'static [u8], Header<'static>, E> {
-        headers(input).map(|x| {
-            let bytes = x.0;
-            let mut vec = x.1;
-            (bytes, vec.pop().unwrap())
-        })
-    }
-    fn headers<E: 'static + FullError<&'static [u8], StompParseError> + std::fmt::Debug>(
-        input: &'static [u8],
-    ) -> IResult<&'static [u8], Vec<Header<'static>>, E> {
-        dbg_dmp(
-            |input| {
-                headers_parser(
-                    Vec::new(),
-                    vec![
-                        HeaderType::HeartBeat,
-                        HeaderType::Destination,
-                        HeaderType::Host,
-                    ],
-                    true,
-                )
-                .parse(input)
-            },
-            "header_line",
-        )(input)
-    }
-
-    fn headers_no_custom<
-        E: 'static + FullError<&'static [u8], StompParseError> + std::fmt::Debug,
-    >(
-        input: &'static [u8],
-    ) -> IResult<&'static [u8], Vec<Header<'static>>, E> {
-        dbg_dmp(
-            |input| {
-                headers_parser(
-                    Vec::new(),
-                    vec![
-                        HeaderType::HeartBeat,
-                        HeaderType::Destination,
-                        HeaderType::Host,
-                    ],
-                    false,
-                )
-                .parse(input)
-            },
-            "header_line",
-        )(input)
+
+    /// Parses the header section, which ends with the blank line, as that of a `SEND` frame.
+    fn send(headers: &str) -> Result<SendFrame<'static>, StompParseError> {
+        match ClientFrame::try_from(format!("SEND\ndestination:d\n{}\x00", headers).into_bytes())? {
+            ClientFrame::Send(frame) => Ok(frame),
+            other => panic!("Expected a SEND frame, got {:?}", other),
+        }
+    }
+
+    /// Parses the header section, which ends with the blank line, as that of a `CONNECT` frame.
+    fn connect(headers: &str) -> Result<ConnectFrame<'static>, StompParseError> {
+        match ClientFrame::try_from(
+            format!("CONNECT\naccept-version:1.2\n{}\x00", headers).into_bytes(),
+        )? {
+            ClientFrame::Connect(frame) => Ok(frame),
+            other => panic!("Expected a CONNECT frame, got {:?}", other),
+        }
     }
+
     fn assert_custom_header(
         input: &'static str,
         expected_key: &'static str,
@@ -227,27 +185,22 @@ mod tests {
         expected_decoded_key: Option<&'static str>,
         expected_decoded_value: Option<&'static str>,
     ) {
-        let result = headers::<VerboseError<&'static [u8]>>(input.as_bytes())
-            .unwrap()
-            .1;
-
-        if let Header::Custom(value) = &result[0] {
-            assert_eq!(expected_key, value.header_name());
-            check_raw_and_decoded(
-                expected_key,
-                value.header_name(),
-                expected_decoded_key,
-                value.decoded_name(),
-            );
-            check_raw_and_decoded(
-                expected_value,
-                value.value(),
-                expected_decoded_value,
-                value.decoded_value(),
-            );
-        } else {
-            panic!("Expected custom header");
-        }
+        let frame = send(input).unwrap();
+        let value = &frame.custom[0];
+
+        assert_eq!(expected_key, value.header_name());
+        check_raw_and_decoded(
+            expected_key,
+            value.header_name(),
+            expected_decoded_key,
+            value.decoded_name(),
+        );
+        check_raw_and_decoded(
+            expected_value,
+            value.value(),
+            expected_decoded_value,
+            value.decoded_value(),
+        );
     }
 
     fn check_raw_and_decoded(
@@ -282,39 +235,57 @@ mod tests {
         assert_custom_header("abc:def\n\n", "abc", "def", Some("abc"), None);
     }
 
+    #[test]
+    fn header_accepts_empty_value() {
+        assert_custom_header("abc:\n\n", "abc", "", Some("abc"), Some(""));
+    }
+
+    #[test]
+    fn header_rejects_empty_name() {
+        let result = send(":def\n\n");
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn header_with_cr_fails() {
-        let result = dbg_dmp(header::<VerboseError<&[u8]>>, "header_line")(b"ab\rc:def\n");
+        let result = send("ab\rc:def\n");
 
         assert!(result.is_err());
     }
 
     #[test]
     fn header_with_nl_fails() {
-        let result = dbg_dmp(header::<VerboseError<&[u8]>>, "header_line")(b"ab\nc:def\n");
+        let result = send("ab\nc:def\n");
 
         assert!(result.is_err());
     }
 
     #[test]
     fn header_with_colon_fails() {
-        let result = dbg_dmp(header::<VerboseError<&[u8]>>, "header_line")(b"abc:d:ef\n");
+        let result = send("abc:d:ef\n");
 
         assert!(result.is_err());
     }
 
-    use nom::bytes::complete::{escaped, is_not};
-    use nom::character::complete::one_of;
+    #[test]
+    fn header_lines_yields_raw_name_and_value() {
+        let mut lines = super::HeaderLines::new(b"a\\rbc:def\n\nbody");
+
+        let (name, value) = lines.next().unwrap().expect("Should be fine");
 
-    fn esc(input: &[u8]) -> IResult<&[u8], &[u8]> {
-        escaped(is_not("\\:\n\r"), '\\', one_of("rn:\\"))(input)
+        assert_eq!("a\\rbc", name);
+        assert_eq!("def", value);
+        assert!(lines.next().is_none());
+        assert_eq!(b"body", lines.remaining());
     }
 
     #[test]
-    fn test_escaped() {
-        let (_, matched) = esc(b"a\\rbc:def\n\n" as &[u8]).expect("Should be fine");
+    fn header_lines_stops_after_error() {
+        let mut lines = super::HeaderLines::new(b"a\\tbc:def\nfoo:bar\n\n");
 
-        assert_eq!(b"a\\rbc", matched)
+        assert!(lines.next().unwrap().is_err());
+        assert!(lines.next().is_none());
     }
 
     #[test]
@@ -345,7 +316,7 @@ mod tests {
 
     #[test]
     fn header_rejects_escaped_tab() {
-        let result = dbg_dmp(header::<VerboseError<&[u8]>>, "header_line")(b"abc:d\\tef\n\n");
+        let result = send("abc:d\\tef\n\n");
 
         assert!(result.is_err());
     }
@@ -363,34 +334,22 @@ mod tests {
 
     #[test]
     fn header_works_for_host() {
-        let header = dbg_dmp(header::<VerboseError<&[u8]>>, "header_line")(b"host:d\\nef\n\n")
-            .unwrap()
-            .1;
-
-        if let Header::Host(value) = header {
-            assert_eq!("d\\nef", value.value());
-        } else {
-            panic!("Expected host header");
-        }
+        let frame = connect("host:d\\nef\n\n").unwrap();
+
+        assert_eq!("d\\nef", frame.host().value());
     }
 
     #[test]
     fn header_works_for_heart_beat() {
-        let header = dbg_dmp(header::<VerboseError<&[u8]>>, "header_line")(b"heart-beat:10,20\n\n")
-            .unwrap()
-            .1;
-
-        if let Header::HeartBeat(value) = header {
-            assert_eq!(
-                HeartBeatIntervals {
-                    supplied: 10,
-                    expected: 20,
-                },
-                *value.value()
-            );
-        } else {
-            panic!("Expected heart-beat header");
-        }
+        let frame = connect("host:h\nheart-beat:10,20\n\n").unwrap();
+
+        assert_eq!(
+            HeartBeatIntervals {
+                supplied: 10,
+                expected: 20,
+            },
+            *frame.heartbeat().value()
+        );
     }
 
     #[test]
@@ -407,65 +366,43 @@ mod tests {
 
     #[test]
     fn headers_works_for_no_headers() {
-        let headers = dbg_dmp(headers::<VerboseError<&[u8]>>, "headers")(b"\n\n")
-            .unwrap()
-            .1;
+        let frame = send("\n").unwrap();
 
-        assert_eq!(0, headers.len());
+        assert_eq!(None, frame.content_type());
+        assert!(frame.custom.is_empty());
     }
 
     #[test]
     fn headers_works_for_single_header() {
-        let headers = dbg_dmp(headers::<VerboseError<&[u8]>>, "headers")(b"heart-beat:10,20\n\n")
-            .unwrap()
-            .1;
+        let frame = send("content-type:text/plain\n\n").unwrap();
 
-        assert_eq!(1, headers.len());
         assert_eq!(
-            Header::HeartBeat(HeartBeatValue::new(HeartBeatIntervals {
-                supplied: 10,
-                expected: 20,
-            })),
-            headers[0]
+            Some("text/plain"),
+            frame.content_type().map(|value| value.value())
         );
+        assert!(frame.custom.is_empty());
     }
 
     #[test]
     fn headers_works_for_multiple_headers() {
-        let headers = dbg_dmp(headers::<VerboseError<&[u8]>>, "headers")(
-            b"heart-beat:10,20\r\nabc:d\\nef\n\n",
-        )
-        .unwrap()
-        .1;
+        let frame = send("content-length:3\r\nabc:d\\nef\n\nabc").unwrap();
 
-        assert_eq!(2, headers.len());
-        assert_eq!(
-            Header::HeartBeat(HeartBeatValue::new(HeartBeatIntervals {
-                supplied: 10,
-                expected: 20,
-            })),
-            headers[0]
-        );
-        assert_eq!(
-            Header::Custom(CustomValue::new("abc", "d\\nef")),
-            headers[1]
-        );
+        assert_eq!(Some(&3), frame.content_length().map(|value| value.value()));
+        assert_eq!(vec![CustomValue::new("abc", "d\\nef")], frame.custom);
+        assert_eq!(Some(&b"abc"[..]), frame.body());
     }
 
     #[test]
     fn headers_rejects_custom_when_disallowed() {
-        let result = dbg_dmp(headers_no_custom::<VerboseError<&[u8]>>, "headers")(
-            b"heart-beat:10,20\r\nabc:d\\nef\n\n",
-        );
+        let result = ClientFrame::try_from(b"ABORT\ntransaction:t\r\nabc:d\\nef\n\n\x00".to_vec());
 
-        assert_eq!(true, result.is_err());
+        assert!(result.is_err());
     }
 
     #[test]
     fn headers_fails_when_no_empty_line() {
-        let headers =
-            dbg_dmp(headers::<VerboseError<&[u8]>>, "headers")(b"heart-beat:10,20\r\nabc:d\\nef\n");
+        let result = send("content-length:0\r\nabc:d\\nef\n");
 
-        assert!(headers.is_err());
+        assert!(result.is_err());
     }
 }
@@ -10,6 +10,37 @@ use nom::{IResult, Needed};
 use crate::error::FullError;
 use crate::error::StompParseError;
 
+/// How the parser validates a `content-length` header against the body of the frame.
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Default)]
+pub enum ContentLengthPolicy {
+    /// The body may be longer than `content-length`, but not shorter, which would mean the frame was truncated.
+    #[default]
+    Lenient,
+    /// The body must be exactly `content-length` bytes long.
+    Strict,
+}
+
+impl ContentLengthPolicy {
+    #[doc(hidden)]
+    pub fn check(self, content_length: u32, body_length: usize) -> Result<(), StompParseError> {
+        let content_length = content_length as usize;
+
+        let valid = match self {
+            ContentLengthPolicy::Lenient => content_length <= body_length,
+            ContentLengthPolicy::Strict => content_length == body_length,
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(StompParseError::new(format!(
+                "content-length of {} does not match body of {} bytes",
+                content_length, body_length
+            )))
+        }
+    }
+}
+
 pub fn null<'a, E: 'a + FullError<&'a [u8], StompParseError>>(
     input: &'a [u8],
 ) -> IResult<&'a [u8], &'a [u8], E> {
@@ -37,6 +68,7 @@ pub fn remaining_without_null<'a, E: FullError<&'a [u8], StompParseError>>(
 
 #[cfg(test)]
 mod tests {
+    use super::ContentLengthPolicy;
     use crate::client::ClientFrame;
     use crate::headers::{AckType, HeartBeatIntervals, StompVersion, StompVersions};
     use std::convert::TryFrom;
@@ -93,7 +125,7 @@ mod tests {
                 .to_owned(),
         );
 
-        assert!(matches!(frame, Err(_)));
+        assert!(frame.is_err());
     }
 
     #[test]
@@ -232,6 +264,38 @@ mod tests {
         }
     }
 
+    #[test]
+    #[allow(non_snake_case)]
+    fn SEND_frame_content_length_is_validated_by_policy() {
+        let frame = |content_length: u32| {
+            format!(
+                "SEND\ndestination:foo\ncontent-length:{}\n\nhello\x00",
+                content_length
+            )
+            .into_bytes()
+        };
+
+        for (content_length, lenient, strict) in
+            [(5, true, true), (4, true, false), (6, false, false)]
+        {
+            assert_eq!(
+                lenient,
+                ClientFrame::parse_with(frame(content_length), ContentLengthPolicy::Lenient)
+                    .is_ok(),
+                "lenient, content-length {}",
+                content_length
+            );
+            assert_eq!(
+                strict,
+                ClientFrame::parse_with(frame(content_length), ContentLengthPolicy::Strict).is_ok(),
+                "strict, content-length {}",
+                content_length
+            );
+        }
+
+        assert!(ClientFrame::try_from(frame(6)).is_err());
+    }
+
     #[test]
     fn it_recognises_abort_frames() {
         let frame = ClientFrame::try_from(b"ABORT\ntransaction:trn-1\n\n\x00".to_vec()).unwrap();
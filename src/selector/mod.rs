@@ -0,0 +1,605 @@
+//! Implements JMS-style message selectors, as supported in the `selector` header of `SUBSCRIBE`
+//! frames by brokers such as ActiveMQ and Artemis.
+//!
+//! A selector is an expression in the subset of SQL-92 defined by the
+//! [JMS specification](https://jakarta.ee/specifications/messaging/3.1/jakarta-messaging-spec-3.1#message-selector),
+//! such as `priority > 4 AND type = 'order'`. [`Selector::parse`] parses it into an [`Expression`],
+//! which can be evaluated against the [`Properties`] of a message, such as a `MessageFrame`.
+//!
+//! Evaluation follows SQL's three-valued logic: comparisons involving absent properties are
+//! unknown, and a selector only matches if it evaluates to true. Since header values are strings,
+//! a string is converted to a number or boolean when it is compared with, or used in arithmetic
+//! with, a number or boolean.
+//!
+//! As selectors are supplied by clients, the parser rejects those nested more than 64 levels deep.
+//! Chains of `AND` and `OR`, however long, are parsed into balanced trees, so they count little
+//! towards this.
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+
+use crate::error::StompParseError;
+use crate::headers::DecodableValue;
+use crate::server::MessageFrame;
+
+mod parser;
+
+/// A literal or property value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOperator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArithmeticOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+/// The syntax tree of a selector.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Value),
+    /// A reference to a property of the message.
+    Identifier(String),
+    Negate(Box<Expression>),
+    Arithmetic(Box<Expression>, ArithmeticOperator, Box<Expression>),
+    Comparison(Box<Expression>, ComparisonOperator, Box<Expression>),
+    Between {
+        expression: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+    },
+    In {
+        expression: Box<Expression>,
+        values: Vec<String>,
+        negated: bool,
+    },
+    /// `%` in the pattern matches any sequence of characters, and `_` any single character, unless
+    /// preceded by the escape character.
+    Like {
+        expression: Box<Expression>,
+        pattern: String,
+        escape: Option<char>,
+        negated: bool,
+    },
+    IsNull {
+        expression: Box<Expression>,
+        negated: bool,
+    },
+    Not(Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+/// The properties of a message which identifiers in a selector refer to.
+pub trait Properties {
+    /// The value of the property with the name, or `None` if the message does not have it.
+    fn property(&self, name: &str) -> Option<Value>;
+}
+
+/// The JMS header fields which have a STOMP equivalent, and the headers they are mapped to.
+const JMS_HEADERS: [(&str, &str); 7] = [
+    ("JMSCorrelationID", "correlation-id"),
+    ("JMSDestination", "destination"),
+    ("JMSExpiration", "expires"),
+    ("JMSMessageID", "message-id"),
+    ("JMSPriority", "priority"),
+    ("JMSTimestamp", "timestamp"),
+    ("JMSType", "type"),
+];
+
+fn decoded<V: DecodableValue>(value: &V) -> Option<Value> {
+    value
+        .decoded_value()
+        .ok()
+        .map(|value| Value::String(value.to_string()))
+}
+
+/// Identifiers refer to the headers of the frame by name; as identifiers cannot contain `-`, the
+/// JMS header fields, such as `JMSMessageID`, can be used for the headers which do.
+impl<'a> Properties for MessageFrame<'a> {
+    fn property(&self, name: &str) -> Option<Value> {
+        let name = JMS_HEADERS
+            .iter()
+            .find(|(jms, _)| *jms == name)
+            .map_or(name, |(_, header)| header);
+
+        match name {
+            "message-id" => decoded(self.message_id()),
+            "destination" => decoded(self.destination()),
+            "subscription" => decoded(self.subscription()),
+            "content-type" => self.content_type().and_then(decoded),
+            "content-length" => self
+                .content_length()
+                .map(|length| Value::Integer(i64::from(*length.value()))),
+            _ => self
+                .custom
+                .iter()
+                .find(|header| header.decoded_name().is_ok_and(|decoded| *decoded == *name))
+                .and_then(decoded),
+        }
+    }
+}
+
+impl<S: BuildHasher> Properties for HashMap<String, Value, S> {
+    fn property(&self, name: &str) -> Option<Value> {
+        self.get(name).cloned()
+    }
+}
+
+/// A parsed message selector.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector {
+    expression: Expression,
+}
+
+impl Selector {
+    /// Parses the selector.
+    pub fn parse(selector: &str) -> Result<Selector, StompParseError> {
+        parser::selector(selector).map(|expression| Selector { expression })
+    }
+
+    /// The syntax tree of the selector.
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    /// Whether the selector evaluates to true for the properties.
+    pub fn matches<P: Properties + ?Sized>(&self, properties: &P) -> bool {
+        self.expression.evaluate(properties) == Some(Value::Boolean(true))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = StompParseError;
+    fn from_str(selector: &str) -> Result<Selector, StompParseError> {
+        Selector::parse(selector)
+    }
+}
+
+impl Expression {
+    /// The number of levels in the expression, which the parser bounds.
+    fn height(&self) -> usize {
+        1 + match self {
+            Expression::Literal(_) | Expression::Identifier(_) => 0,
+            Expression::Negate(expression)
+            | Expression::Not(expression)
+            | Expression::In { expression, .. }
+            | Expression::Like { expression, .. }
+            | Expression::IsNull { expression, .. } => expression.height(),
+            Expression::Arithmetic(left, _, right)
+            | Expression::Comparison(left, _, right)
+            | Expression::And(left, right)
+            | Expression::Or(left, right) => left.height().max(right.height()),
+            Expression::Between {
+                expression,
+                low,
+                high,
+                ..
+            } => expression.height().max(low.height()).max(high.height()),
+        }
+    }
+
+    /// Evaluates the expression for the properties; `None` is SQL's null or unknown.
+    pub fn evaluate<P: Properties + ?Sized>(&self, properties: &P) -> Option<Value> {
+        match self {
+            Expression::Literal(value) => Some(value.clone()),
+            Expression::Identifier(name) => properties.property(name),
+            Expression::Negate(expression) => match number(&expression.evaluate(properties)?)? {
+                Number::Integer(value) => value.checked_neg().map(Value::Integer),
+                Number::Float(value) => Some(Value::Float(-value)),
+            },
+            Expression::Arithmetic(left, operator, right) => arithmetic(
+                number(&left.evaluate(properties)?)?,
+                *operator,
+                number(&right.evaluate(properties)?)?,
+            ),
+            Expression::Comparison(left, operator, right) => compare(
+                &left.evaluate(properties)?,
+                *operator,
+                &right.evaluate(properties)?,
+            )
+            .map(Value::Boolean),
+            Expression::Between {
+                expression,
+                low,
+                high,
+                negated,
+            } => {
+                let value = expression.evaluate(properties);
+                let within = and(
+                    value.as_ref().and_then(|value| {
+                        compare(
+                            value,
+                            ComparisonOperator::GreaterOrEqual,
+                            &low.evaluate(properties)?,
+                        )
+                    }),
+                    value.as_ref().and_then(|value| {
+                        compare(
+                            value,
+                            ComparisonOperator::LessOrEqual,
+                            &high.evaluate(properties)?,
+                        )
+                    }),
+                );
+                within.map(|within| Value::Boolean(within != *negated))
+            }
+            Expression::In {
+                expression,
+                values,
+                negated,
+            } => match expression.evaluate(properties)? {
+                Value::String(value) => Some(Value::Boolean(values.contains(&value) != *negated)),
+                _ => None,
+            },
+            Expression::Like {
+                expression,
+                pattern,
+                escape,
+                negated,
+            } => match expression.evaluate(properties)? {
+                Value::String(value) => Some(Value::Boolean(
+                    like(&value, &compile(pattern, *escape)) != *negated,
+                )),
+                _ => None,
+            },
+            Expression::IsNull {
+                expression,
+                negated,
+            } => Some(Value::Boolean(
+                expression.evaluate(properties).is_none() != *negated,
+            )),
+            Expression::Not(expression) => {
+                truth(expression.evaluate(properties)).map(|truth| Value::Boolean(!truth))
+            }
+            Expression::And(left, right) => and(
+                truth(left.evaluate(properties)),
+                truth(right.evaluate(properties)),
+            )
+            .map(Value::Boolean),
+            Expression::Or(left, right) => or(
+                truth(left.evaluate(properties)),
+                truth(right.evaluate(properties)),
+            )
+            .map(Value::Boolean),
+        }
+    }
+}
+
+fn truth(value: Option<Value>) -> Option<bool> {
+    match value? {
+        Value::Boolean(value) => Some(value),
+        Value::String(value) => boolean(&value),
+        _ => None,
+    }
+}
+
+fn and(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+fn or(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+fn boolean(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn float(self) -> f64 {
+        match self {
+            Number::Integer(value) => value as f64,
+            Number::Float(value) => value,
+        }
+    }
+}
+
+fn number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Integer(value) => Some(Number::Integer(*value)),
+        Value::Float(value) => Some(Number::Float(*value)),
+        Value::String(value) => i64::from_str(value.trim())
+            .map(Number::Integer)
+            .or_else(|_| f64::from_str(value.trim()).map(Number::Float))
+            .ok(),
+        Value::Boolean(_) => None,
+    }
+}
+
+fn arithmetic(left: Number, operator: ArithmeticOperator, right: Number) -> Option<Value> {
+    match (left, right) {
+        (Number::Integer(left), Number::Integer(right)) => match operator {
+            ArithmeticOperator::Add => left.checked_add(right),
+            ArithmeticOperator::Subtract => left.checked_sub(right),
+            ArithmeticOperator::Multiply => left.checked_mul(right),
+            ArithmeticOperator::Divide => left.checked_div(right),
+        }
+        .map(Value::Integer),
+        (left, right) => {
+            let (left, right) = (left.float(), right.float());
+            Some(Value::Float(match operator {
+                ArithmeticOperator::Add => left + right,
+                ArithmeticOperator::Subtract => left - right,
+                ArithmeticOperator::Multiply => left * right,
+                ArithmeticOperator::Divide => left / right,
+            }))
+        }
+    }
+}
+
+/// Compares the values; strings and booleans only support equality.
+fn compare(left: &Value, operator: ComparisonOperator, right: &Value) -> Option<bool> {
+    let ordering = match (left, right) {
+        (Value::String(left), Value::String(right)) => {
+            return equality(left == right, operator);
+        }
+        (Value::Boolean(left), Value::Boolean(right)) => {
+            return equality(left == right, operator);
+        }
+        (Value::Boolean(left), Value::String(right))
+        | (Value::String(right), Value::Boolean(left)) => {
+            return equality(Some(*left) == boolean(right), operator);
+        }
+        (left, right) => match (number(left)?, number(right)?) {
+            (Number::Integer(left), Number::Integer(right)) => left.cmp(&right),
+            (left, right) => left.float().partial_cmp(&right.float())?,
+        },
+    };
+
+    Some(match operator {
+        ComparisonOperator::Equal => ordering == Ordering::Equal,
+        ComparisonOperator::NotEqual => ordering != Ordering::Equal,
+        ComparisonOperator::Less => ordering == Ordering::Less,
+        ComparisonOperator::LessOrEqual => ordering != Ordering::Greater,
+        ComparisonOperator::Greater => ordering == Ordering::Greater,
+        ComparisonOperator::GreaterOrEqual => ordering != Ordering::Less,
+    })
+}
+
+fn equality(equal: bool, operator: ComparisonOperator) -> Option<bool> {
+    match operator {
+        ComparisonOperator::Equal => Some(equal),
+        ComparisonOperator::NotEqual => Some(!equal),
+        _ => None,
+    }
+}
+
+enum Wildcard {
+    Char(char),
+    AnyChar,
+    AnyChars,
+}
+
+fn compile(pattern: &str, escape: Option<char>) -> Vec<Wildcard> {
+    let mut compiled = Vec::with_capacity(pattern.len());
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        compiled.push(match c {
+            c if Some(c) == escape => Wildcard::Char(chars.next().unwrap_or(c)),
+            '%' => Wildcard::AnyChars,
+            '_' => Wildcard::AnyChar,
+            c => Wildcard::Char(c),
+        });
+    }
+
+    compiled
+}
+
+/// Matches the value against the pattern, backtracking to the most recent `%` on a mismatch.
+fn like(value: &str, pattern: &[Wildcard]) -> bool {
+    let value: Vec<char> = value.chars().collect();
+    let (mut v, mut p) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some(Wildcard::AnyChars) => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(Wildcard::AnyChar) => {
+                v += 1;
+                p += 1;
+            }
+            Some(Wildcard::Char(c)) if *c == value[v] => {
+                v += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                Some((any_chars, matched)) => {
+                    // Let the `%` match one more character
+                    backtrack = Some((any_chars, matched + 1));
+                    p = any_chars + 1;
+                    v = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..]
+        .iter()
+        .all(|wildcard| matches!(wildcard, Wildcard::AnyChars))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::server::{MessageFrame, ServerFrame};
+    use std::convert::TryFrom;
+
+    fn properties() -> HashMap<String, Value> {
+        vec![
+            ("priority", Value::Integer(7)),
+            ("price", Value::Float(9.5)),
+            ("type", Value::String("order".to_owned())),
+            ("count", Value::String("12".to_owned())),
+            ("urgent", Value::Boolean(true)),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), value))
+        .collect()
+    }
+
+    #[test]
+    fn evaluates_conformance_table() {
+        let properties = properties();
+
+        let cases = [
+            ("priority > 4 AND type = 'order'", true),
+            ("priority > 7", false),
+            ("priority >= 7 AND priority <= 7 AND priority <> 8", true),
+            ("price < 10", true),
+            ("price * 2 = 19", true),
+            ("priority / 2 = 3", true),
+            ("-priority + 10 = 3", true),
+            ("count > 10", true),
+            ("count = '12'", true),
+            ("type > 'a'", false),
+            ("urgent", true),
+            ("urgent = TRUE AND NOT urgent = FALSE", true),
+            ("priority BETWEEN 5 AND 10", true),
+            ("priority NOT BETWEEN 5 AND 10", false),
+            ("type IN ('order', 'quote')", true),
+            ("type NOT IN ('order', 'quote')", false),
+            ("type LIKE 'or%'", true),
+            ("type LIKE '_rde_'", true),
+            ("type LIKE 'o%d%r'", true),
+            ("type LIKE 'order_'", false),
+            ("type NOT LIKE '%x%'", true),
+            ("missing IS NULL", true),
+            ("type IS NOT NULL", true),
+            ("missing = 1", false),
+            ("NOT missing = 1", false),
+            ("missing = 1 OR priority = 7", true),
+            ("missing = 1 AND priority = 7", false),
+            ("missing BETWEEN 1 AND 2", false),
+            ("missing IN ('a')", false),
+            ("priority / 0 = 1", false),
+            ("type = 1", false),
+        ];
+
+        for (selector, expected) in cases.iter() {
+            assert_eq!(
+                *expected,
+                Selector::parse(selector).unwrap().matches(&properties),
+                "evaluating {}",
+                selector
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_is_neither_true_nor_false() {
+        let properties = properties();
+
+        assert_eq!(
+            None,
+            Selector::parse("missing > 1 OR type = 'quote'")
+                .unwrap()
+                .expression()
+                .evaluate(&properties)
+        );
+        assert_eq!(
+            Some(Value::Boolean(true)),
+            Selector::parse("missing > 1 OR type = 'order'")
+                .unwrap()
+                .expression()
+                .evaluate(&properties)
+        );
+    }
+
+    #[test]
+    fn like_honours_escape() {
+        let properties: HashMap<String, Value> =
+            vec![("code".to_owned(), Value::String("100%_off".to_owned()))]
+                .into_iter()
+                .collect();
+
+        assert!(Selector::parse("code LIKE '100!%!_off' ESCAPE '!'")
+            .unwrap()
+            .matches(&properties));
+        assert!(!Selector::parse("code LIKE '100!%!_of' ESCAPE '!'")
+            .unwrap()
+            .matches(&properties));
+    }
+
+    #[test]
+    fn evaluates_against_message_frame() {
+        let frame = ServerFrame::try_from(
+            b"MESSAGE\nmessage-id:m-1\ndestination:/queue/a\nsubscription:s-1\ncontent-length:0\n\
+            priority:9\ntype:order\nreply-to:/queue/b\\cc\n\n\x00"
+                .to_vec(),
+        )
+        .unwrap();
+        let ServerFrame::Message(frame) = frame else {
+            panic!("Message Frame not parsed correctly");
+        };
+
+        for selector in [
+            "priority > 4 AND type = 'order'",
+            "JMSPriority = 9",
+            "JMSMessageID = 'm-1' AND JMSDestination LIKE '/queue/%'",
+            "destination = '/queue/a' AND subscription = 's-1'",
+            "JMSCorrelationID IS NULL",
+        ] {
+            assert!(
+                Selector::parse(selector).unwrap().matches(&frame),
+                "evaluating {}",
+                selector
+            );
+        }
+
+        let frame = MessageFrame::builder()
+            .message_id("m-2".to_owned())
+            .destination("a".to_owned())
+            .subscription("s".to_owned())
+            .add_custom_header("colour".to_owned(), "red:ish".to_owned())
+            .build();
+
+        assert!(Selector::parse("colour = 'red:ish'")
+            .unwrap()
+            .matches(&frame));
+    }
+}
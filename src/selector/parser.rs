@@ -0,0 +1,524 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, tag_no_case, take_while, take_while1};
+use nom::character::complete::{char, digit0, digit1, multispace0, none_of, one_of};
+use nom::combinator::{all_consuming, map, map_res, not, opt, peek, recognize, value, verify};
+use nom::error::{ErrorKind, ParseError, VerboseError};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+use super::{ArithmeticOperator, ComparisonOperator, Expression, Value};
+use crate::error::StompParseError;
+
+type Res<'a, T> = IResult<&'a str, T, VerboseError<&'a str>>;
+
+const KEYWORDS: [&str; 11] = [
+    "and", "between", "escape", "false", "in", "is", "like", "not", "null", "or", "true",
+];
+
+/// The maximum nesting of a selector. Selectors are supplied by clients, so this bounds the
+/// recursion in parsing, evaluating and dropping them.
+const MAX_DEPTH: usize = 64;
+
+/// Parses a complete selector expression.
+pub fn selector(input: &str) -> Result<Expression, StompParseError> {
+    all_consuming(terminated(|input| or(input, 0), multispace0))(input)
+        .map(|(_, expression)| expression)
+        .map_err(|error| StompParseError::new(format!("Invalid selector '{}': {:?}", input, error)))
+}
+
+fn ws<'a, O>(parser: impl FnMut(&'a str) -> Res<'a, O>) -> impl FnMut(&'a str) -> Res<'a, O> {
+    preceded(multispace0, parser)
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+fn keyword<'a>(keyword: &'static str) -> impl FnMut(&'a str) -> Res<'a, &'a str> {
+    ws(terminated(
+        tag_no_case(keyword),
+        not(peek(take_while1(is_identifier_part))),
+    ))
+}
+
+fn boxed(expression: Expression) -> Box<Expression> {
+    Box::new(expression)
+}
+
+/// Fails, without backtracking, if the depth exceeds [`MAX_DEPTH`].
+fn limit(input: &str, depth: usize) -> Res<'_, ()> {
+    if depth > MAX_DEPTH {
+        Err(nom::Err::Failure(VerboseError::from_error_kind(
+            input,
+            ErrorKind::TooLarge,
+        )))
+    } else {
+        Ok((input, ()))
+    }
+}
+
+fn bounded(input: &str, expression: Expression) -> Res<'_, Expression> {
+    limit(input, expression.height()).map(|(input, _)| (input, expression))
+}
+
+/// A left-associative chain of operands, separated by the operator.
+fn chain<'a, O>(
+    input: &'a str,
+    depth: usize,
+    operand: fn(&'a str, usize) -> Res<'a, Expression>,
+    mut operator: impl FnMut(&'a str) -> Res<'a, O>,
+    combine: fn(Expression, O, Expression) -> Expression,
+) -> Res<'a, Expression> {
+    let (mut input, mut expression) = operand(input, depth)?;
+
+    loop {
+        match pair(&mut operator, |input| operand(input, depth))(input) {
+            Ok((rest, (operator, right))) => {
+                let (rest, combined) = bounded(rest, combine(expression, operator, right))?;
+                input = rest;
+                expression = combined;
+            }
+            Err(nom::Err::Error(_)) => return Ok((input, expression)),
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// A chain of operands, separated by the operator, which is associative, combined into a balanced
+/// tree. Long flat chains, such as `a = 1 OR a = 2 OR ...`, are therefore only as deep as the
+/// logarithm of their length, rather than being bounded by [`MAX_DEPTH`].
+fn junction<'a>(
+    input: &'a str,
+    depth: usize,
+    operand: fn(&'a str, usize) -> Res<'a, Expression>,
+    operator: &'static str,
+    combine: fn(Box<Expression>, Box<Expression>) -> Expression,
+) -> Res<'a, Expression> {
+    let (input, operands) =
+        separated_list1(keyword(operator), |input| operand(input, depth))(input)?;
+    bounded(input, balanced(operands, combine))
+}
+
+fn balanced(
+    mut operands: Vec<Expression>,
+    combine: fn(Box<Expression>, Box<Expression>) -> Expression,
+) -> Expression {
+    if operands.len() == 1 {
+        operands.remove(0)
+    } else {
+        let right = operands.split_off(operands.len().div_ceil(2));
+        combine(
+            boxed(balanced(operands, combine)),
+            boxed(balanced(right, combine)),
+        )
+    }
+}
+
+fn or(input: &str, depth: usize) -> Res<'_, Expression> {
+    let (input, _) = limit(input, depth)?;
+    junction(input, depth, and, "or", Expression::Or)
+}
+
+fn and(input: &str, depth: usize) -> Res<'_, Expression> {
+    junction(input, depth, negation, "and", Expression::And)
+}
+
+fn negation(input: &str, depth: usize) -> Res<'_, Expression> {
+    let (input, _) = limit(input, depth)?;
+    alt((
+        map(
+            preceded(keyword("not"), |input| negation(input, depth + 1)),
+            |expression| Expression::Not(boxed(expression)),
+        ),
+        |input| predicate(input, depth),
+    ))(input)
+}
+
+/// A sum, optionally followed by a comparison or one of the other predicates.
+fn predicate(input: &str, depth: usize) -> Res<'_, Expression> {
+    let (input, expression) = sum(input, depth)?;
+    let (input, predicate) = opt(alt((
+        |input| between(input, depth),
+        in_list,
+        like,
+        is_null,
+        map(
+            pair(ws(comparison_operator), |input| sum(input, depth)),
+            |(operator, right)| Predicate::Comparison(operator, right),
+        ),
+    )))(input)?;
+
+    let expression = match predicate {
+        None => expression,
+        Some(Predicate::Comparison(operator, right)) => {
+            Expression::Comparison(boxed(expression), operator, boxed(right))
+        }
+        Some(Predicate::Between(negated, low, high)) => Expression::Between {
+            expression: boxed(expression),
+            low: boxed(low),
+            high: boxed(high),
+            negated,
+        },
+        Some(Predicate::In(negated, values)) => Expression::In {
+            expression: boxed(expression),
+            values,
+            negated,
+        },
+        Some(Predicate::Like(negated, pattern, escape)) => Expression::Like {
+            expression: boxed(expression),
+            pattern,
+            escape,
+            negated,
+        },
+        Some(Predicate::IsNull(negated)) => Expression::IsNull {
+            expression: boxed(expression),
+            negated,
+        },
+    };
+
+    Ok((input, expression))
+}
+
+enum Predicate {
+    Comparison(ComparisonOperator, Expression),
+    Between(bool, Expression, Expression),
+    In(bool, Vec<String>),
+    Like(bool, String, Option<char>),
+    IsNull(bool),
+}
+
+fn negated(input: &str) -> Res<'_, bool> {
+    map(opt(keyword("not")), |not| not.is_some())(input)
+}
+
+fn between(input: &str, depth: usize) -> Res<'_, Predicate> {
+    map(
+        tuple((
+            terminated(negated, keyword("between")),
+            |input| sum(input, depth),
+            preceded(keyword("and"), |input| sum(input, depth)),
+        )),
+        |(negated, low, high)| Predicate::Between(negated, low, high),
+    )(input)
+}
+
+fn in_list(input: &str) -> Res<'_, Predicate> {
+    map(
+        pair(
+            terminated(negated, keyword("in")),
+            delimited(
+                ws(char('(')),
+                separated_list1(ws(char(',')), ws(string)),
+                ws(char(')')),
+            ),
+        ),
+        |(negated, values)| Predicate::In(negated, values),
+    )(input)
+}
+
+fn like(input: &str) -> Res<'_, Predicate> {
+    map(
+        tuple((
+            terminated(negated, keyword("like")),
+            ws(string),
+            opt(preceded(
+                keyword("escape"),
+                map_res(ws(string), |escape| {
+                    let mut chars = escape.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(escape), None) => Ok(escape),
+                        _ => Err("The escape must be a single character"),
+                    }
+                }),
+            )),
+        )),
+        |(negated, pattern, escape)| Predicate::Like(negated, pattern, escape),
+    )(input)
+}
+
+fn is_null(input: &str) -> Res<'_, Predicate> {
+    map(
+        delimited(keyword("is"), negated, keyword("null")),
+        Predicate::IsNull,
+    )(input)
+}
+
+fn comparison_operator(input: &str) -> Res<'_, ComparisonOperator> {
+    alt((
+        value(ComparisonOperator::NotEqual, tag("<>")),
+        value(ComparisonOperator::LessOrEqual, tag("<=")),
+        value(ComparisonOperator::GreaterOrEqual, tag(">=")),
+        value(ComparisonOperator::Equal, tag("=")),
+        value(ComparisonOperator::Less, tag("<")),
+        value(ComparisonOperator::Greater, tag(">")),
+    ))(input)
+}
+
+fn sum(input: &str, depth: usize) -> Res<'_, Expression> {
+    chain(
+        input,
+        depth,
+        product,
+        ws(alt((
+            value(ArithmeticOperator::Add, char('+')),
+            value(ArithmeticOperator::Subtract, char('-')),
+        ))),
+        |left, operator, right| Expression::Arithmetic(boxed(left), operator, boxed(right)),
+    )
+}
+
+fn product(input: &str, depth: usize) -> Res<'_, Expression> {
+    chain(
+        input,
+        depth,
+        unary,
+        ws(alt((
+            value(ArithmeticOperator::Multiply, char('*')),
+            value(ArithmeticOperator::Divide, char('/')),
+        ))),
+        |left, operator, right| Expression::Arithmetic(boxed(left), operator, boxed(right)),
+    )
+}
+
+fn unary(input: &str, depth: usize) -> Res<'_, Expression> {
+    let (input, _) = limit(input, depth)?;
+    alt((
+        map(
+            preceded(ws(char('-')), |input| unary(input, depth + 1)),
+            |expression| Expression::Negate(boxed(expression)),
+        ),
+        preceded(ws(char('+')), |input| unary(input, depth + 1)),
+        |input| primary(input, depth),
+    ))(input)
+}
+
+fn primary(input: &str, depth: usize) -> Res<'_, Expression> {
+    alt((
+        delimited(ws(char('(')), |input| or(input, depth + 1), ws(char(')'))),
+        map(ws(literal), Expression::Literal),
+        map(ws(identifier), |identifier| {
+            Expression::Identifier(identifier.to_owned())
+        }),
+    ))(input)
+}
+
+fn literal(input: &str) -> Res<'_, Value> {
+    alt((
+        map(string, Value::String),
+        value(Value::Boolean(true), keyword("true")),
+        value(Value::Boolean(false), keyword("false")),
+        number,
+    ))(input)
+}
+
+/// A string literal, in single quotes; a quote is escaped by doubling it.
+fn string(input: &str) -> Res<'_, String> {
+    delimited(
+        char('\''),
+        map(
+            many0(alt((value('\'', tag("''")), none_of("'")))),
+            |chars| chars.into_iter().collect(),
+        ),
+        char('\''),
+    )(input)
+}
+
+fn number(input: &str) -> Res<'_, Value> {
+    let exponent = || recognize(tuple((one_of("eE"), opt(one_of("+-")), digit1)));
+
+    terminated(
+        alt((
+            map_res(
+                alt((
+                    recognize(tuple((digit1, char('.'), digit0, opt(exponent())))),
+                    recognize(tuple((char('.'), digit1, opt(exponent())))),
+                    recognize(pair(digit1, exponent())),
+                )),
+                |float: &str| float.parse().map(Value::Float),
+            ),
+            map_res(terminated(digit1, opt(one_of("lL"))), |integer: &str| {
+                integer.parse().map(Value::Integer)
+            }),
+        )),
+        not(peek(take_while1(is_identifier_part))),
+    )(input)
+}
+
+fn identifier(input: &str) -> Res<'_, &str> {
+    verify(
+        recognize(pair(
+            take_while1(is_identifier_start),
+            take_while(is_identifier_part),
+        )),
+        |identifier: &str| {
+            !KEYWORDS
+                .iter()
+                .any(|keyword| keyword.eq_ignore_ascii_case(identifier))
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn identifier(name: &str) -> Box<Expression> {
+        boxed(Expression::Identifier(name.to_owned()))
+    }
+
+    fn integer(value: i64) -> Box<Expression> {
+        boxed(Expression::Literal(Value::Integer(value)))
+    }
+
+    fn string(value: &str) -> Box<Expression> {
+        boxed(Expression::Literal(Value::String(value.to_owned())))
+    }
+
+    #[test]
+    fn parses_comparisons_with_boolean_logic() {
+        assert_eq!(
+            Expression::Or(
+                boxed(Expression::And(
+                    boxed(Expression::Comparison(
+                        identifier("priority"),
+                        ComparisonOperator::Greater,
+                        integer(4)
+                    )),
+                    boxed(Expression::Comparison(
+                        identifier("type"),
+                        ComparisonOperator::Equal,
+                        string("order")
+                    )),
+                )),
+                boxed(Expression::Not(boxed(Expression::Comparison(
+                    identifier("urgent"),
+                    ComparisonOperator::NotEqual,
+                    boxed(Expression::Literal(Value::Boolean(true)))
+                )))),
+            ),
+            selector("priority > 4 AND type = 'order' or NOT urgent<>TRUE").unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_arithmetic_with_precedence() {
+        assert_eq!(
+            Expression::Comparison(
+                boxed(Expression::Arithmetic(
+                    identifier("a"),
+                    ArithmeticOperator::Subtract,
+                    boxed(Expression::Arithmetic(
+                        integer(2),
+                        ArithmeticOperator::Multiply,
+                        boxed(Expression::Negate(identifier("b")))
+                    ))
+                )),
+                ComparisonOperator::LessOrEqual,
+                boxed(Expression::Literal(Value::Float(1.5e2))),
+            ),
+            selector("a-2*-b <= 1.5e2").unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_predicates() {
+        assert_eq!(
+            Expression::Between {
+                expression: identifier("a"),
+                low: integer(1),
+                high: integer(10),
+                negated: true
+            },
+            selector("a NOT BETWEEN 1 AND 10").unwrap()
+        );
+        assert_eq!(
+            Expression::In {
+                expression: identifier("country"),
+                values: vec!["UK".to_owned(), "it's".to_owned()],
+                negated: false
+            },
+            selector("country in ( 'UK','it''s' )").unwrap()
+        );
+        assert_eq!(
+            Expression::Like {
+                expression: identifier("name"),
+                pattern: "a\\_%".to_owned(),
+                escape: Some('\\'),
+                negated: false
+            },
+            selector("name LIKE 'a\\_%' ESCAPE '\\'").unwrap()
+        );
+        assert_eq!(
+            Expression::IsNull {
+                expression: identifier("name"),
+                negated: true
+            },
+            selector("name IS NOT NULL").unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_literals() {
+        assert_eq!(
+            Expression::Literal(Value::Integer(42)),
+            selector("42L").unwrap()
+        );
+        assert_eq!(
+            Expression::Literal(Value::Float(0.5)),
+            selector(".5").unwrap()
+        );
+        assert_eq!(
+            Expression::Literal(Value::Boolean(false)),
+            selector(" false ").unwrap()
+        );
+        assert_eq!(
+            Expression::Identifier("$trueish_1".to_owned()),
+            selector("$trueish_1").unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_selectors() {
+        for invalid in [
+            "",
+            "a =",
+            "a = 'unterminated",
+            "and = 1",
+            "a LIKE 'b' ESCAPE 'cd'",
+            "a IN ()",
+            "(a = 1",
+            "a = 1 b",
+            "12abc = 1",
+        ] {
+            assert!(
+                selector(invalid).is_err(),
+                "'{}' should be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_deeply_nested_selectors() {
+        let nested = |depth: usize, open: &str, close: &str| {
+            format!("{}a = 1{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        assert!(selector(&nested(MAX_DEPTH / 2, "(", ")")).is_ok());
+        assert!(selector(&format!("a = 1{}", " OR a = 1 AND b = 2".repeat(10_000))).is_ok());
+
+        for deep in [
+            nested(100_000, "(", ")"),
+            nested(100_000, "NOT ", ""),
+            format!("a = {}1", "-".repeat(100_000)),
+            format!("a = 1{}", " + 1".repeat(100_000)),
+        ] {
+            assert!(selector(&deep).is_err());
+        }
+    }
+}